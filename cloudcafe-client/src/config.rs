@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::PathBuf;
+use color_eyre::{Report, Result};
+use serde::{Deserialize, Serialize};
+use crate::internal_os::monitor_select::MonitorMatcher;
+use crate::keybindings::KeybindingConfig;
+use crate::service::DEFAULT_FRIENDLY_NAME;
+use crate::virtual_manager::tiling::TilingStrategy;
+
+const CONFIG_FILE: &'static str = "config.toml";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    pub monitor: Option<MonitorMatcher>,
+    pub tiling: TilingStrategy,
+    pub workspaces: Vec<String>,
+    pub curvature: f32,
+    pub resolution: Option<[u32; 2]>,
+    pub driver_name: String,
+    pub keybindings: KeybindingConfig,
+}
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            monitor: None,
+            tiling: TilingStrategy::default(),
+            workspaces: Vec::new(),
+            curvature: 1.0,
+            resolution: None,
+            driver_name: String::from(DEFAULT_FRIENDLY_NAME),
+            keybindings: KeybindingConfig::default(),
+        }
+    }
+}
+pub fn config_dir() -> Result<PathBuf> {
+    let app_data = std::env::var("APPDATA").map_err(|_| Report::msg("APPDATA is not set"))?;
+    Ok(PathBuf::from(app_data).join("CloudCafe"))
+}
+
+impl UserConfig {
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join(CONFIG_FILE))
+    }
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|err| Report::msg(format!("invalid {CONFIG_FILE}: {err}")))
+    }
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
@@ -1,19 +1,30 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, MutexGuard};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
-use std::thread::JoinHandle;
-use willhook::{InputEvent, KeyboardEvent, KeyboardKey, KeyPress, MouseButton, MouseButtonPress, MouseEvent, MouseEventType, MousePressEvent, willhook};
-use crate::input::Key::Q;
+use std::time::{Duration, Instant};
+use willhook::{InputEvent, KeyboardEvent, KeyboardKey, KeyPress, MouseButton, MouseButtonPress, MouseEvent, MouseEventType, MousePressEvent, MouseWheel, MouseWheelDirection, MouseWheelEvent, willhook};
+use crate::worker::{RestartPolicy, Worker};
+
+const HOOK_IDLE: Duration = Duration::from_millis(1);
+const MAX_QUEUED_EVENTS: usize = 1024;
+const HOOK_RESTART: RestartPolicy = RestartPolicy::OnPanic { max_restarts: 3, backoff: Duration::from_millis(500) };
 
 #[derive(Debug, Copy, Clone, PartialEq, Hash, Eq)]
 pub enum Key {
     MouseLeft,
     MouseRight,
+    MouseMiddle,
+    MouseX1,
+    MouseX2,
     Windows,
     Backspace,
     ArrowDown,
     ArrowUp,
+    ArrowLeft,
+    ArrowRight,
+    Shift,
+    Alt,
+    Control,
     Enter,
     A,
     B,
@@ -40,14 +51,126 @@ pub enum Key {
     W,
     X,
     Y,
-    Z
+    Z,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Tab,
+    Escape,
+    Space,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Insert,
+    Delete,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    Comma,
+    Period,
+    Minus,
+    Equal,
+    Semicolon,
+    Quote,
+    Backtick,
+    Slash,
+    Backslash,
+    LeftBracket,
+    RightBracket,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPrevTrack,
+    MediaStop,
+    MediaPlayPause,
 }
 impl Key {
+    pub const ALL: [Key; 123] = [
+        Key::MouseLeft, Key::MouseRight, Key::MouseMiddle, Key::MouseX1, Key::MouseX2, Key::Windows, Key::Backspace, Key::ArrowDown,
+        Key::ArrowUp, Key::ArrowLeft, Key::ArrowRight, Key::Shift, Key::Alt, Key::Control, Key::Enter, Key::A,
+        Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+        Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q,
+        Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y,
+        Key::Z, Key::Digit0, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4, Key::Digit5, Key::Digit6,
+        Key::Digit7, Key::Digit8, Key::Digit9, Key::Tab, Key::Escape, Key::Space, Key::PageUp, Key::PageDown,
+        Key::Home, Key::End, Key::Insert, Key::Delete, Key::CapsLock, Key::NumLock, Key::ScrollLock, Key::F1,
+        Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9,
+        Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15, Key::F16, Key::F17,
+        Key::F18, Key::F19, Key::F20, Key::F21, Key::F22, Key::F23, Key::F24, Key::Numpad0,
+        Key::Numpad1, Key::Numpad2, Key::Numpad3, Key::Numpad4, Key::Numpad5, Key::Numpad6, Key::Numpad7, Key::Numpad8,
+        Key::Numpad9, Key::NumpadAdd, Key::NumpadSubtract, Key::NumpadMultiply, Key::NumpadDivide, Key::NumpadDecimal, Key::Comma, Key::Period,
+        Key::Minus, Key::Equal, Key::Semicolon, Key::Quote, Key::Backtick, Key::Slash, Key::Backslash, Key::LeftBracket,
+        Key::RightBracket, Key::BrowserBack, Key::BrowserForward, Key::BrowserRefresh, Key::VolumeMute, Key::VolumeDown, Key::VolumeUp, Key::MediaNextTrack,
+        Key::MediaPrevTrack, Key::MediaStop, Key::MediaPlayPause,
+    ];
     pub fn as_str<'a>(&self) -> &'a str {
         match self {
             Key::MouseLeft => "MouseLeft",
+            Key::MouseRight => "MouseRight",
+            Key::MouseMiddle => "MouseMiddle",
+            Key::MouseX1 => "MouseX1",
+            Key::MouseX2 => "MouseX2",
             Key::Windows => "Windows",
             Key::Backspace => "Backspace",
+            Key::ArrowDown => "ArrowDown",
+            Key::ArrowUp => "ArrowUp",
+            Key::ArrowLeft => "ArrowLeft",
+            Key::ArrowRight => "ArrowRight",
+            Key::Shift => "Shift",
+            Key::Alt => "Alt",
+            Key::Control => "Control",
+            Key::Enter => "Enter",
             Key::A => "a",
             Key::B => "b",
             Key::C => "c",
@@ -74,9 +197,139 @@ impl Key {
             Key::X => "x",
             Key::Y => "y",
             Key::Z => "z",
-            _ => panic!()
+            Key::Digit0 => "0",
+            Key::Digit1 => "1",
+            Key::Digit2 => "2",
+            Key::Digit3 => "3",
+            Key::Digit4 => "4",
+            Key::Digit5 => "5",
+            Key::Digit6 => "6",
+            Key::Digit7 => "7",
+            Key::Digit8 => "8",
+            Key::Digit9 => "9",
+            Key::Tab => "Tab",
+            Key::Escape => "Escape",
+            Key::Space => " ",
+            Key::PageUp => "PageUp",
+            Key::PageDown => "PageDown",
+            Key::Home => "Home",
+            Key::End => "End",
+            Key::Insert => "Insert",
+            Key::Delete => "Delete",
+            Key::CapsLock => "CapsLock",
+            Key::NumLock => "NumLock",
+            Key::ScrollLock => "ScrollLock",
+            Key::F1 => "F1",
+            Key::F2 => "F2",
+            Key::F3 => "F3",
+            Key::F4 => "F4",
+            Key::F5 => "F5",
+            Key::F6 => "F6",
+            Key::F7 => "F7",
+            Key::F8 => "F8",
+            Key::F9 => "F9",
+            Key::F10 => "F10",
+            Key::F11 => "F11",
+            Key::F12 => "F12",
+            Key::F13 => "F13",
+            Key::F14 => "F14",
+            Key::F15 => "F15",
+            Key::F16 => "F16",
+            Key::F17 => "F17",
+            Key::F18 => "F18",
+            Key::F19 => "F19",
+            Key::F20 => "F20",
+            Key::F21 => "F21",
+            Key::F22 => "F22",
+            Key::F23 => "F23",
+            Key::F24 => "F24",
+            Key::Numpad0 => "0",
+            Key::Numpad1 => "1",
+            Key::Numpad2 => "2",
+            Key::Numpad3 => "3",
+            Key::Numpad4 => "4",
+            Key::Numpad5 => "5",
+            Key::Numpad6 => "6",
+            Key::Numpad7 => "7",
+            Key::Numpad8 => "8",
+            Key::Numpad9 => "9",
+            Key::NumpadAdd => "+",
+            Key::NumpadSubtract => "-",
+            Key::NumpadMultiply => "*",
+            Key::NumpadDivide => "/",
+            Key::NumpadDecimal => ".",
+            Key::Comma => ",",
+            Key::Period => ".",
+            Key::Minus => "-",
+            Key::Equal => "=",
+            Key::Semicolon => ";",
+            Key::Quote => "'",
+            Key::Backtick => "`",
+            Key::Slash => "/",
+            Key::Backslash => "\\",
+            Key::LeftBracket => "[",
+            Key::RightBracket => "]",
+            Key::BrowserBack => "BrowserBack",
+            Key::BrowserForward => "BrowserForward",
+            Key::BrowserRefresh => "BrowserRefresh",
+            Key::VolumeMute => "VolumeMute",
+            Key::VolumeDown => "VolumeDown",
+            Key::VolumeUp => "VolumeUp",
+            Key::MediaNextTrack => "MediaNextTrack",
+            Key::MediaPrevTrack => "MediaPrevTrack",
+            Key::MediaStop => "MediaStop",
+            Key::MediaPlayPause => "MediaPlayPause",
         }
     }
+    pub fn char(&self, shift: bool) -> Option<char> {
+        let (plain, shifted) = match self {
+            Key::Digit0 => ('0', ')'),
+            Key::Digit1 => ('1', '!'),
+            Key::Digit2 => ('2', '@'),
+            Key::Digit3 => ('3', '#'),
+            Key::Digit4 => ('4', '$'),
+            Key::Digit5 => ('5', '%'),
+            Key::Digit6 => ('6', '^'),
+            Key::Digit7 => ('7', '&'),
+            Key::Digit8 => ('8', '*'),
+            Key::Digit9 => ('9', '('),
+            Key::Comma => (',', '<'),
+            Key::Period => ('.', '>'),
+            Key::Minus => ('-', '_'),
+            Key::Equal => ('=', '+'),
+            Key::Semicolon => (';', ':'),
+            Key::Quote => ('\'', '"'),
+            Key::Backtick => ('`', '~'),
+            Key::Slash => ('/', '?'),
+            Key::Backslash => ('\\', '|'),
+            Key::LeftBracket => ('[', '{'),
+            Key::RightBracket => (']', '}'),
+            Key::Space => (' ', ' '),
+            Key::Numpad0 => ('0', '0'),
+            Key::Numpad1 => ('1', '1'),
+            Key::Numpad2 => ('2', '2'),
+            Key::Numpad3 => ('3', '3'),
+            Key::Numpad4 => ('4', '4'),
+            Key::Numpad5 => ('5', '5'),
+            Key::Numpad6 => ('6', '6'),
+            Key::Numpad7 => ('7', '7'),
+            Key::Numpad8 => ('8', '8'),
+            Key::Numpad9 => ('9', '9'),
+            Key::NumpadAdd => ('+', '+'),
+            Key::NumpadSubtract => ('-', '-'),
+            Key::NumpadMultiply => ('*', '*'),
+            Key::NumpadDivide => ('/', '/'),
+            Key::NumpadDecimal => ('.', '.'),
+            _ => {
+                let text = self.as_str();
+                match (text.len(), text.chars().next()) {
+                    (1, Some(letter)) if letter.is_ascii_lowercase() => (letter, letter.to_ascii_uppercase()),
+                    _ => return None,
+                }
+            }
+        };
+        Some(if shift { shifted } else { plain })
+    }
 }
 #[derive(PartialEq, Hash, Debug, Copy, Clone, Default)]
 pub struct InputState {
@@ -91,186 +344,450 @@ impl InputState {
         }
     }
 }
-fn keyboard_press(key: Key, key_press: KeyPress, keys: &mut MutexGuard<HashMap<Key, InputState>>) {
-    match key_press {
-        KeyPress::Down(_) => {
-            keys.insert(key, InputState::new(true, true));
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Default)]
+pub struct WheelDelta {
+    pub vertical: i32,
+    pub horizontal: i32,
+}
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub windows: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HookEvent {
+    KeyDown(Key),
+    KeyUp(Key),
+    Char(char),
+    MouseButton { button: Key, pressed: bool },
+    Wheel(WheelDelta),
+}
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimedEvent {
+    pub time: Instant,
+    pub modifiers: Modifiers,
+    pub event: HookEvent,
+}
+pub fn clicked(events: &[TimedEvent], button: Key) -> bool {
+    events.iter().any(|event| event.event == HookEvent::MouseButton { button, pressed: true })
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookState {
+    keys: HashMap<Key, InputState>,
+    wheel: WheelDelta,
+    queue: VecDeque<TimedEvent>,
+}
+impl HookState {
+    pub fn new() -> Self {
+        Self {
+            keys: Key::ALL.iter().map(|key| (*key, InputState::default())).collect(),
+            wheel: WheelDelta::default(),
+            queue: VecDeque::new(),
+        }
+    }
+    pub fn get(&self, key: Key) -> InputState {
+        *self.keys.get(&key).expect("key should be in keys map")
+    }
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            windows: self.get(Key::Windows).active,
+            control: self.get(Key::Control).active,
+            alt: self.get(Key::Alt).active,
+            shift: self.get(Key::Shift).active,
+        }
+    }
+    pub fn take_wheel(&mut self) -> WheelDelta {
+        std::mem::take(&mut self.wheel)
+    }
+    pub fn take_events(&mut self) -> Vec<TimedEvent> {
+        self.queue.drain(..).collect()
+    }
+    pub fn reset_active(&mut self) {
+        for (_, key_state) in self.keys.iter_mut() {
+            key_state.just_changed = false;
+        }
+    }
+    fn apply(&mut self, event: HookEvent) {
+        match event {
+            HookEvent::KeyDown(key) => {
+                self.keys.insert(key, InputState::new(true, true));
+            }
+            HookEvent::KeyUp(key) => {
+                self.keys.insert(key, InputState::new(false, true));
+            }
+            HookEvent::MouseButton { button, pressed } => {
+                self.keys.insert(button, InputState::new(pressed, true));
+            }
+            HookEvent::Wheel(delta) => {
+                self.wheel.vertical += delta.vertical;
+                self.wheel.horizontal += delta.horizontal;
+            }
+            HookEvent::Char(_) => {}
         }
-        KeyPress::Up(_) => {
-            keys.insert(key, InputState::new(false, true));
+    }
+    fn push(&mut self, event: HookEvent, time: Instant) {
+        self.apply(event);
+        if self.queue.len() == MAX_QUEUED_EVENTS {
+            self.queue.pop_front();
         }
-        KeyPress::Other(_) => {}
+        self.queue.push_back(TimedEvent {
+            time,
+            modifiers: self.modifiers(),
+            event,
+        });
+    }
+}
+pub fn keyboard_key(key: KeyboardKey) -> Option<Key> {
+    match key {
+        KeyboardKey::LeftWindows | KeyboardKey::RightWindows => Some(Key::Windows),
+        KeyboardKey::BackSpace => Some(Key::Backspace),
+        KeyboardKey::ArrowDown => Some(Key::ArrowDown),
+        KeyboardKey::ArrowUp => Some(Key::ArrowUp),
+        KeyboardKey::ArrowLeft => Some(Key::ArrowLeft),
+        KeyboardKey::ArrowRight => Some(Key::ArrowRight),
+        KeyboardKey::LeftShift | KeyboardKey::RightShift => Some(Key::Shift),
+        KeyboardKey::LeftAlt | KeyboardKey::RightAlt => Some(Key::Alt),
+        KeyboardKey::LeftControl | KeyboardKey::RightControl => Some(Key::Control),
+        KeyboardKey::Enter => Some(Key::Enter),
+        KeyboardKey::A => Some(Key::A),
+        KeyboardKey::B => Some(Key::B),
+        KeyboardKey::C => Some(Key::C),
+        KeyboardKey::D => Some(Key::D),
+        KeyboardKey::E => Some(Key::E),
+        KeyboardKey::F => Some(Key::F),
+        KeyboardKey::G => Some(Key::G),
+        KeyboardKey::H => Some(Key::H),
+        KeyboardKey::I => Some(Key::I),
+        KeyboardKey::J => Some(Key::J),
+        KeyboardKey::K => Some(Key::K),
+        KeyboardKey::L => Some(Key::L),
+        KeyboardKey::M => Some(Key::M),
+        KeyboardKey::N => Some(Key::N),
+        KeyboardKey::O => Some(Key::O),
+        KeyboardKey::P => Some(Key::P),
+        KeyboardKey::Q => Some(Key::Q),
+        KeyboardKey::R => Some(Key::R),
+        KeyboardKey::S => Some(Key::S),
+        KeyboardKey::T => Some(Key::T),
+        KeyboardKey::U => Some(Key::U),
+        KeyboardKey::V => Some(Key::V),
+        KeyboardKey::W => Some(Key::W),
+        KeyboardKey::X => Some(Key::X),
+        KeyboardKey::Y => Some(Key::Y),
+        KeyboardKey::Z => Some(Key::Z),
+        KeyboardKey::Number0 => Some(Key::Digit0),
+        KeyboardKey::Number1 => Some(Key::Digit1),
+        KeyboardKey::Number2 => Some(Key::Digit2),
+        KeyboardKey::Number3 => Some(Key::Digit3),
+        KeyboardKey::Number4 => Some(Key::Digit4),
+        KeyboardKey::Number5 => Some(Key::Digit5),
+        KeyboardKey::Number6 => Some(Key::Digit6),
+        KeyboardKey::Number7 => Some(Key::Digit7),
+        KeyboardKey::Number8 => Some(Key::Digit8),
+        KeyboardKey::Number9 => Some(Key::Digit9),
+        KeyboardKey::Tab => Some(Key::Tab),
+        KeyboardKey::Escape => Some(Key::Escape),
+        KeyboardKey::Space => Some(Key::Space),
+        KeyboardKey::PageUp => Some(Key::PageUp),
+        KeyboardKey::PageDown => Some(Key::PageDown),
+        KeyboardKey::Home => Some(Key::Home),
+        KeyboardKey::End => Some(Key::End),
+        KeyboardKey::Insert => Some(Key::Insert),
+        KeyboardKey::Delete => Some(Key::Delete),
+        KeyboardKey::CapsLock => Some(Key::CapsLock),
+        KeyboardKey::NumLock => Some(Key::NumLock),
+        KeyboardKey::ScrollLock => Some(Key::ScrollLock),
+        KeyboardKey::F1 => Some(Key::F1),
+        KeyboardKey::F2 => Some(Key::F2),
+        KeyboardKey::F3 => Some(Key::F3),
+        KeyboardKey::F4 => Some(Key::F4),
+        KeyboardKey::F5 => Some(Key::F5),
+        KeyboardKey::F6 => Some(Key::F6),
+        KeyboardKey::F7 => Some(Key::F7),
+        KeyboardKey::F8 => Some(Key::F8),
+        KeyboardKey::F9 => Some(Key::F9),
+        KeyboardKey::F10 => Some(Key::F10),
+        KeyboardKey::F11 => Some(Key::F11),
+        KeyboardKey::F12 => Some(Key::F12),
+        KeyboardKey::F13 => Some(Key::F13),
+        KeyboardKey::F14 => Some(Key::F14),
+        KeyboardKey::F15 => Some(Key::F15),
+        KeyboardKey::F16 => Some(Key::F16),
+        KeyboardKey::F17 => Some(Key::F17),
+        KeyboardKey::F18 => Some(Key::F18),
+        KeyboardKey::F19 => Some(Key::F19),
+        KeyboardKey::F20 => Some(Key::F20),
+        KeyboardKey::F21 => Some(Key::F21),
+        KeyboardKey::F22 => Some(Key::F22),
+        KeyboardKey::F23 => Some(Key::F23),
+        KeyboardKey::F24 => Some(Key::F24),
+        KeyboardKey::NumPad0 => Some(Key::Numpad0),
+        KeyboardKey::NumPad1 => Some(Key::Numpad1),
+        KeyboardKey::NumPad2 => Some(Key::Numpad2),
+        KeyboardKey::NumPad3 => Some(Key::Numpad3),
+        KeyboardKey::NumPad4 => Some(Key::Numpad4),
+        KeyboardKey::NumPad5 => Some(Key::Numpad5),
+        KeyboardKey::NumPad6 => Some(Key::Numpad6),
+        KeyboardKey::NumPad7 => Some(Key::Numpad7),
+        KeyboardKey::NumPad8 => Some(Key::Numpad8),
+        KeyboardKey::NumPad9 => Some(Key::Numpad9),
+        KeyboardKey::Add => Some(Key::NumpadAdd),
+        KeyboardKey::Subtract => Some(Key::NumpadSubtract),
+        KeyboardKey::Multiply => Some(Key::NumpadMultiply),
+        KeyboardKey::Divide => Some(Key::NumpadDivide),
+        KeyboardKey::Decimal => Some(Key::NumpadDecimal),
+        KeyboardKey::Comma => Some(Key::Comma),
+        KeyboardKey::Period => Some(Key::Period),
+        KeyboardKey::Minus => Some(Key::Minus),
+        KeyboardKey::Equal => Some(Key::Equal),
+        KeyboardKey::SemiColon => Some(Key::Semicolon),
+        KeyboardKey::Apostrophe => Some(Key::Quote),
+        KeyboardKey::BackTick => Some(Key::Backtick),
+        KeyboardKey::ForwardSlash => Some(Key::Slash),
+        KeyboardKey::BackwardSlash => Some(Key::Backslash),
+        KeyboardKey::LeftBrace => Some(Key::LeftBracket),
+        KeyboardKey::RightBrace => Some(Key::RightBracket),
+        KeyboardKey::BrowserBack => Some(Key::BrowserBack),
+        KeyboardKey::BrowserForward => Some(Key::BrowserForward),
+        KeyboardKey::BrowserRefresh => Some(Key::BrowserRefresh),
+        KeyboardKey::VolumeMute => Some(Key::VolumeMute),
+        KeyboardKey::VolumeDown => Some(Key::VolumeDown),
+        KeyboardKey::VolumeUp => Some(Key::VolumeUp),
+        KeyboardKey::MediaNextTrack => Some(Key::MediaNextTrack),
+        KeyboardKey::MediaPrevTrack => Some(Key::MediaPrevTrack),
+        KeyboardKey::MediaStop => Some(Key::MediaStop),
+        KeyboardKey::MediaPlayPause => Some(Key::MediaPlayPause),
+        _ => None,
+    }
+}
+pub fn mouse_button(button: MouseButton) -> Option<Key> {
+    match button {
+        MouseButton::Left(_) => Some(Key::MouseLeft),
+        MouseButton::Right(_) => Some(Key::MouseRight),
+        MouseButton::Middle(_) => Some(Key::MouseMiddle),
+        MouseButton::X1(_) => Some(Key::MouseX1),
+        MouseButton::X2(_) => Some(Key::MouseX2),
+        MouseButton::UnkownX(_) => None,
+        MouseButton::Other(_) => None,
+    }
+}
+pub fn translate_event(event: InputEvent, shift: bool) -> Vec<HookEvent> {
+    match event {
+        InputEvent::Keyboard(KeyboardEvent { pressed, key, .. }) => {
+            let key = match key.and_then(keyboard_key) {
+                None => return vec![],
+                Some(key) => key,
+            };
+            match pressed {
+                KeyPress::Down(_) => [Some(HookEvent::KeyDown(key)), key.char(shift).map(HookEvent::Char)].into_iter().flatten().collect(),
+                KeyPress::Up(_) => vec![HookEvent::KeyUp(key)],
+                KeyPress::Other(_) => vec![],
+            }
+        }
+        InputEvent::Mouse(MouseEvent { event, .. }) => {
+            match event {
+                MouseEventType::Press(MousePressEvent { pressed, button }) => {
+                    let button = match mouse_button(button) {
+                        None => return vec![],
+                        Some(button) => button,
+                    };
+                    match pressed {
+                        MouseButtonPress::Down => vec![HookEvent::MouseButton { button, pressed: true }],
+                        MouseButtonPress::Up => vec![HookEvent::MouseButton { button, pressed: false }],
+                        MouseButtonPress::Other(_) => vec![],
+                    }
+                }
+                MouseEventType::Wheel(MouseWheelEvent { wheel, direction }) => {
+                    let notches = match direction {
+                        MouseWheelDirection::Forward => 1,
+                        MouseWheelDirection::Backward => -1,
+                        _ => return vec![],
+                    };
+                    match wheel {
+                        MouseWheel::Vertical => vec![HookEvent::Wheel(WheelDelta { vertical: notches, horizontal: 0 })],
+                        MouseWheel::Horizontal => vec![HookEvent::Wheel(WheelDelta { vertical: 0, horizontal: notches })],
+                        _ => vec![],
+                    }
+                }
+                MouseEventType::Move(_) => vec![],
+                MouseEventType::Other(_) => vec![],
+            }
+        }
+        InputEvent::Other(_) => vec![],
+    }
+}
+pub fn apply_event(state: &mut HookState, event: InputEvent, time: Instant) {
+    let shift = state.get(Key::Shift).active;
+    for event in translate_event(event, shift) {
+        state.push(event, time);
     }
 }
 pub struct KeyboardMouseState {
-    keys: Arc<Mutex<HashMap<Key, InputState>>>,
-    thread: JoinHandle<()>,
+    state: Arc<Mutex<HookState>>,
+    _hook_worker: Worker,
 }
 impl KeyboardMouseState {
     pub fn new() -> Self {
-        let mut keys = HashMap::new();
-        keys.insert(Key::MouseLeft, InputState::default());
-        keys.insert(Key::MouseRight, InputState::default());
-        keys.insert(Key::Windows, InputState::default());
-        keys.insert(Key::Backspace, InputState::default());
-        keys.insert(Key::Enter, InputState::default());
-        keys.insert(Key::ArrowUp, InputState::default());
-        keys.insert(Key::ArrowDown, InputState::default());
-        keys.insert(Key::A, InputState::default());
-        keys.insert(Key::B, InputState::default());
-        keys.insert(Key::C, InputState::default());
-        keys.insert(Key::D, InputState::default());
-        keys.insert(Key::E, InputState::default());
-        keys.insert(Key::F, InputState::default());
-        keys.insert(Key::G, InputState::default());
-        keys.insert(Key::H, InputState::default());
-        keys.insert(Key::I, InputState::default());
-        keys.insert(Key::J, InputState::default());
-        keys.insert(Key::K, InputState::default());
-        keys.insert(Key::L, InputState::default());
-        keys.insert(Key::M, InputState::default());
-        keys.insert(Key::N, InputState::default());
-        keys.insert(Key::O, InputState::default());
-        keys.insert(Key::P, InputState::default());
-        keys.insert(Key::Q, InputState::default());
-        keys.insert(Key::R, InputState::default());
-        keys.insert(Key::S, InputState::default());
-        keys.insert(Key::T, InputState::default());
-        keys.insert(Key::U, InputState::default());
-        keys.insert(Key::V, InputState::default());
-        keys.insert(Key::W, InputState::default());
-        keys.insert(Key::X, InputState::default());
-        keys.insert(Key::Y, InputState::default());
-        keys.insert(Key::Z, InputState::default());
-
-        let keys = Arc::new(Mutex::new(keys));
-        let keys_2 = keys.clone();
+        let state = Arc::new(Mutex::new(HookState::new()));
+        let hook_state = state.clone();
 
-        let thread = thread::spawn(move || {
-            let is_running = Arc::new(AtomicBool::new(true));
+        let hook_worker = Worker::spawn("input hook", HOOK_RESTART, move |cancel| {
             let hook = willhook().unwrap();
 
-            while is_running.load(Ordering::SeqCst) {
+            while !cancel.is_cancelled() {
                 if let Ok(ie) = hook.try_recv() {
-                    let mut keys = keys.lock().unwrap();
-                    match ie {
-                        InputEvent::Keyboard(keyboard) => {
-                            match keyboard {
-                                KeyboardEvent { pressed, key, is_injected } => {
-                                    match key {
-                                        None => {}
-                                        Some(key) => {
-                                            match key {
-                                                KeyboardKey::LeftWindows => {
-                                                    keyboard_press(Key::Windows, pressed, &mut keys);
-                                                }
-                                                KeyboardKey::RightWindows => {
-                                                    keyboard_press(Key::Windows, pressed, &mut keys);
-                                                }
-                                                KeyboardKey::Enter => keyboard_press(Key::Enter, pressed, &mut keys),
-                                                KeyboardKey::ArrowUp => keyboard_press(Key::ArrowUp, pressed, &mut keys),
-                                                KeyboardKey::ArrowDown => keyboard_press(Key::ArrowDown, pressed, &mut keys),
-                                                KeyboardKey::BackSpace => keyboard_press(Key::Backspace, pressed, &mut keys),
-                                                KeyboardKey::A => keyboard_press(Key::A, pressed, &mut keys),
-                                                KeyboardKey::B => keyboard_press(Key::B, pressed, &mut keys),
-                                                KeyboardKey::C => keyboard_press(Key::C, pressed, &mut keys),
-                                                KeyboardKey::D => keyboard_press(Key::D, pressed, &mut keys),
-                                                KeyboardKey::E => keyboard_press(Key::E, pressed, &mut keys),
-                                                KeyboardKey::F => keyboard_press(Key::F, pressed, &mut keys),
-                                                KeyboardKey::G => keyboard_press(Key::G, pressed, &mut keys),
-                                                KeyboardKey::H => keyboard_press(Key::H, pressed, &mut keys),
-                                                KeyboardKey::I => keyboard_press(Key::I, pressed, &mut keys),
-                                                KeyboardKey::J => keyboard_press(Key::J, pressed, &mut keys),
-                                                KeyboardKey::K => keyboard_press(Key::K, pressed, &mut keys),
-                                                KeyboardKey::L => keyboard_press(Key::L, pressed, &mut keys),
-                                                KeyboardKey::M => keyboard_press(Key::M, pressed, &mut keys),
-                                                KeyboardKey::N => keyboard_press(Key::N, pressed, &mut keys),
-                                                KeyboardKey::O => keyboard_press(Key::O, pressed, &mut keys),
-                                                KeyboardKey::P => keyboard_press(Key::P, pressed, &mut keys),
-                                                KeyboardKey::Q => keyboard_press(Key::Q, pressed, &mut keys),
-                                                KeyboardKey::R => keyboard_press(Key::R, pressed, &mut keys),
-                                                KeyboardKey::S => keyboard_press(Key::S, pressed, &mut keys),
-                                                KeyboardKey::T => keyboard_press(Key::T, pressed, &mut keys),
-                                                KeyboardKey::U => keyboard_press(Key::U, pressed, &mut keys),
-                                                KeyboardKey::V => keyboard_press(Key::V, pressed, &mut keys),
-                                                KeyboardKey::W => keyboard_press(Key::W, pressed, &mut keys),
-                                                KeyboardKey::X => keyboard_press(Key::X, pressed, &mut keys),
-                                                KeyboardKey::Y => keyboard_press(Key::Y, pressed, &mut keys),
-                                                KeyboardKey::Z => keyboard_press(Key::Z, pressed, &mut keys),
-                                                _ => {}
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        InputEvent::Mouse(mouse) => {
-                            match mouse {
-                                MouseEvent { event, is_injected } => {
-                                    match event {
-                                        MouseEventType::Press(press) => {
-                                            match press {
-                                                MousePressEvent { pressed, button } => {
-                                                    match button {
-                                                        MouseButton::Left(click) => {
-                                                            match pressed {
-                                                                MouseButtonPress::Down => {
-                                                                    keys.insert(Key::MouseLeft, InputState::new(true, true));
-                                                                }
-                                                                MouseButtonPress::Up => {
-                                                                    keys.insert(Key::MouseLeft, InputState::new(false, true));
-                                                                }
-                                                                MouseButtonPress::Other(_) => {}
-                                                            }
-                                                        }
-                                                        MouseButton::Right(_) => {
-                                                            match pressed {
-                                                                MouseButtonPress::Down => {
-                                                                    keys.insert(Key::MouseRight, InputState::new(true, true));
-                                                                }
-                                                                MouseButtonPress::Up => {
-                                                                    keys.insert(Key::MouseRight, InputState::new(false, true));
-                                                                }
-                                                                MouseButtonPress::Other(_) => {}
-                                                            }
-                                                        }
-                                                        MouseButton::Middle(_) => {}
-                                                        MouseButton::X1(_) => {}
-                                                        MouseButton::X2(_) => {}
-                                                        MouseButton::UnkownX(_) => {}
-                                                        MouseButton::Other(_) => {}
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        MouseEventType::Move(_) => {}
-                                        MouseEventType::Wheel(_) => {}
-                                        MouseEventType::Other(_) => {}
-                                    }
-                                }
-                            }
-                        }
-                        InputEvent::Other(_) => {}
-                    }
+                    apply_event(&mut hook_state.lock().unwrap_or_else(PoisonError::into_inner), ie, Instant::now());
+                } else {
+                    thread::sleep(HOOK_IDLE);
                 }
             }
-
         });
 
-
         Self {
-            keys: keys_2,
-            thread,
+            state,
+            _hook_worker: hook_worker,
         }
     }
     pub fn get_input(&mut self, key: Key) -> InputState {
-        let mut keys = self.keys.lock().unwrap();
-        let return_state = *keys.get(&key).expect("key should be in keys map");
-        return_state
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).get(key)
+    }
+    pub fn take_wheel(&mut self) -> WheelDelta {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).take_wheel()
+    }
+    pub fn drain_events(&mut self) -> Vec<TimedEvent> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).take_events()
     }
     pub fn reset_active(&mut self) {
-        for (_, key_state) in self.keys.lock().unwrap().iter_mut() {
-            key_state.just_changed = false;
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).reset_active();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use willhook::{IsSystemKeyPress, MouseClick, MouseMoveEvent};
+    use super::*;
+
+    fn key(key: KeyboardKey, down: bool) -> InputEvent {
+        InputEvent::Keyboard(KeyboardEvent {
+            pressed: match down {
+                true => KeyPress::Down(IsSystemKeyPress::Normal),
+                false => KeyPress::Up(IsSystemKeyPress::Normal),
+            },
+            key: Some(key),
+            is_injected: None,
+        })
+    }
+    fn mouse(event: MouseEventType) -> InputEvent {
+        InputEvent::Mouse(MouseEvent { event, is_injected: None })
+    }
+    fn button(button: MouseButton, pressed: MouseButtonPress) -> InputEvent {
+        mouse(MouseEventType::Press(MousePressEvent { pressed, button }))
+    }
+    fn wheel(wheel: MouseWheel, direction: MouseWheelDirection) -> InputEvent {
+        mouse(MouseEventType::Wheel(MouseWheelEvent { wheel, direction }))
+    }
+    fn events(state: &mut HookState) -> Vec<HookEvent> {
+        state.take_events().into_iter().map(|timed| timed.event).collect()
+    }
+
+    #[test]
+    fn keys_go_down_and_up() {
+        let mut state = HookState::new();
+        let time = Instant::now();
+        apply_event(&mut state, key(KeyboardKey::A, true), time);
+        assert_eq!(state.get(Key::A), InputState::new(true, true));
+        state.reset_active();
+        assert_eq!(state.get(Key::A), InputState::new(true, false));
+        apply_event(&mut state, key(KeyboardKey::A, false), time);
+        assert_eq!(state.get(Key::A), InputState::new(false, true));
+        apply_event(&mut state, key(KeyboardKey::F5, true), time);
+        assert_eq!(events(&mut state), vec![HookEvent::KeyDown(Key::A), HookEvent::Char('a'), HookEvent::KeyUp(Key::A), HookEvent::KeyDown(Key::F5)]);
+    }
+
+    #[test]
+    fn left_and_right_modifiers_collapse() {
+        let pairs = [
+            (KeyboardKey::LeftShift, KeyboardKey::RightShift, Key::Shift),
+            (KeyboardKey::LeftControl, KeyboardKey::RightControl, Key::Control),
+            (KeyboardKey::LeftAlt, KeyboardKey::RightAlt, Key::Alt),
+            (KeyboardKey::LeftWindows, KeyboardKey::RightWindows, Key::Windows),
+        ];
+        for (left, right, collapsed) in pairs {
+            assert_eq!(translate_event(key(left, true), false), vec![HookEvent::KeyDown(collapsed)]);
+            assert_eq!(translate_event(key(right, false), false), vec![HookEvent::KeyUp(collapsed)]);
+        }
+        let mut state = HookState::new();
+        apply_event(&mut state, key(KeyboardKey::RightShift, true), Instant::now());
+        apply_event(&mut state, key(KeyboardKey::Number1, true), Instant::now());
+        let pressed = state.take_events();
+        assert_eq!(pressed.last().unwrap().event, HookEvent::Char('!'));
+        assert_eq!(pressed.last().unwrap().modifiers, Modifiers { shift: true, ..Modifiers::default() });
+    }
+
+    #[test]
+    fn every_mouse_button_is_tracked() {
+        let buttons = [
+            (MouseButton::Left(MouseClick::SingleClick), Key::MouseLeft),
+            (MouseButton::Right(MouseClick::SingleClick), Key::MouseRight),
+            (MouseButton::Middle(MouseClick::SingleClick), Key::MouseMiddle),
+            (MouseButton::X1(MouseClick::SingleClick), Key::MouseX1),
+            (MouseButton::X2(MouseClick::DoubleClick), Key::MouseX2),
+        ];
+        let mut state = HookState::new();
+        for (pressed, key) in buttons {
+            apply_event(&mut state, button(pressed, MouseButtonPress::Down), Instant::now());
+            assert_eq!(state.get(key), InputState::new(true, true));
+            apply_event(&mut state, button(pressed, MouseButtonPress::Up), Instant::now());
+            assert_eq!(state.get(key), InputState::new(false, true));
+            assert_eq!(events(&mut state), vec![HookEvent::MouseButton { button: key, pressed: true }, HookEvent::MouseButton { button: key, pressed: false }]);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn clicks_survive_a_release_in_the_same_frame() {
+        let mut state = HookState::new();
+        let left = MouseButton::Left(MouseClick::SingleClick);
+        apply_event(&mut state, button(left, MouseButtonPress::Down), Instant::now());
+        apply_event(&mut state, button(left, MouseButtonPress::Up), Instant::now());
+        assert!(!state.get(Key::MouseLeft).active);
+        let events = state.take_events();
+        assert!(clicked(&events, Key::MouseLeft));
+        assert!(!clicked(&events, Key::MouseRight));
+        assert!(!clicked(&state.take_events(), Key::MouseLeft));
+    }
+
+    #[test]
+    fn wheel_accumulates_on_both_axes() {
+        let mut state = HookState::new();
+        let time = Instant::now();
+        apply_event(&mut state, wheel(MouseWheel::Vertical, MouseWheelDirection::Forward), time);
+        apply_event(&mut state, wheel(MouseWheel::Vertical, MouseWheelDirection::Forward), time);
+        apply_event(&mut state, wheel(MouseWheel::Horizontal, MouseWheelDirection::Backward), time);
+        assert_eq!(state.take_wheel(), WheelDelta { vertical: 2, horizontal: -1 });
+        assert_eq!(state.take_wheel(), WheelDelta::default());
+        assert_eq!(events(&mut state)[2], HookEvent::Wheel(WheelDelta { vertical: 0, horizontal: -1 }));
+    }
+
+    #[test]
+    fn unmapped_input_is_dropped() {
+        let unmapped = [
+            key(KeyboardKey::Other(0xff), true),
+            InputEvent::Keyboard(KeyboardEvent { pressed: KeyPress::Down(IsSystemKeyPress::Normal), key: None, is_injected: None }),
+            InputEvent::Keyboard(KeyboardEvent { pressed: KeyPress::Other(0), key: Some(KeyboardKey::A), is_injected: None }),
+            button(MouseButton::UnkownX(MouseClick::SingleClick), MouseButtonPress::Down),
+            button(MouseButton::Left(MouseClick::SingleClick), MouseButtonPress::Other(0)),
+            wheel(MouseWheel::Unknown(0), MouseWheelDirection::Forward),
+            wheel(MouseWheel::Vertical, MouseWheelDirection::Unknown(0)),
+            mouse(MouseEventType::Move(MouseMoveEvent { point: Some((1, 2)) })),
+            InputEvent::Other(0),
+        ];
+        let mut state = HookState::new();
+        for event in unmapped {
+            assert_eq!(translate_event(event, false), vec![], "{event:?}");
+            apply_event(&mut state, event, Instant::now());
+        }
+        assert_eq!(state, HookState::new());
+    }
+}
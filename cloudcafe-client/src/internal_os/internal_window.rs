@@ -29,6 +29,15 @@ impl IWindow {
             }
         }
     }
+    pub fn monitor(&self) -> FakeMonitor {
+        self.fake_monitor
+    }
+    pub fn padding(&self) -> u32 {
+        self.padding as u32
+    }
+    pub fn set_monitor(&mut self, fake_monitor: FakeMonitor) {
+        self.fake_monitor = fake_monitor;
+    }
     pub fn size_changed(&self) -> bool {
         if let Some(size) = self.size() {
             if size != self.stored_size {
@@ -1,10 +1,15 @@
 use windows::Win32::Graphics::Gdi::HMONITOR;
 use crate::values::{IVec2, UVec2};
-use crate::windows_bindings::Rect;
+use crate::internal_os::monitor_select::DisplayDescriptor;
+use sysinfo::{Pid, PidExt, ProcessExt, SystemExt};
+use crate::windows_bindings::{class_name, display_device, Hwnd, monitor_device, Rect, window_process_id, window_title};
 use color_eyre::{Report, Result};
 
 pub mod internal_mouse;
 pub mod internal_window;
+pub mod monitor_select;
+pub mod window_events;
+pub mod window_hook;
 
 #[derive(Copy, Clone, Debug)]
 pub struct FakeMonitor {
@@ -28,4 +33,46 @@ impl FakeMonitor {
             size,
         })
     }
-}
\ No newline at end of file
+}
+pub fn describe_display(handle: HMONITOR, rect: Rect) -> DisplayDescriptor {
+    let mut descriptor = DisplayDescriptor {
+        handle: handle.0,
+        left: rect.left,
+        top: rect.top,
+        width: (rect.right - rect.left).max(0) as u32,
+        height: (rect.bottom - rect.top).max(0) as u32,
+        ..Default::default()
+    };
+    if let Some(monitor) = monitor_device(handle) {
+        if let Some(device) = display_device(&monitor.device_name) {
+            descriptor.adapter_name = device.adapter_name;
+            descriptor.adapter_id = device.adapter_id;
+            descriptor.monitor_id = device.monitor_id;
+        }
+        descriptor.device_name = monitor.device_name;
+        descriptor.primary = monitor.primary;
+    }
+    descriptor
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WindowIdentity {
+    pub exe: String,
+    pub class: String,
+    pub title: String,
+}
+
+pub fn identify_window(hwnd: Hwnd) -> WindowIdentity {
+    let exe = window_process_id(hwnd).and_then(|pid| {
+        let pid = Pid::from_u32(pid);
+        let mut system = sysinfo::System::new();
+        system.refresh_process(pid);
+        let exe = system.process(pid)?.exe().file_name()?.to_string_lossy().to_ascii_lowercase();
+        Some(exe)
+    });
+    WindowIdentity {
+        exe: exe.unwrap_or_default(),
+        class: class_name(hwnd).unwrap_or_default(),
+        title: window_title(hwnd).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MONITOR_WIDTH: u32 = 4000;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DisplayDescriptor {
+    pub handle: isize,
+    pub device_name: String,
+    pub adapter_name: String,
+    pub adapter_id: String,
+    pub monitor_id: String,
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub primary: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorMatcher {
+    DeviceName(String),
+    AdapterName(String),
+    AdapterId(String),
+    MonitorId(String),
+    Resolution {
+        width: u32,
+        height: Option<u32>,
+    },
+}
+impl MonitorMatcher {
+    pub fn check(&self, display: &DisplayDescriptor) -> Result<(), String> {
+        match self {
+            MonitorMatcher::DeviceName(name) => {
+                if display.device_name.eq_ignore_ascii_case(name) {
+                    return Ok(());
+                }
+                Err(format!("device name {} is not {}", display.device_name, name))
+            }
+            MonitorMatcher::AdapterName(name) => {
+                if display.adapter_name.eq_ignore_ascii_case(name) {
+                    return Ok(());
+                }
+                Err(format!("adapter \"{}\" is not \"{}\"", display.adapter_name, name))
+            }
+            MonitorMatcher::AdapterId(id) => {
+                if display.adapter_id.to_ascii_lowercase().contains(&id.to_ascii_lowercase()) {
+                    return Ok(());
+                }
+                Err(format!("adapter id {} does not contain {}", display.adapter_id, id))
+            }
+            MonitorMatcher::MonitorId(id) => {
+                if display.monitor_id.to_ascii_lowercase().contains(&id.to_ascii_lowercase()) {
+                    return Ok(());
+                }
+                Err(format!("monitor id {} does not contain {}", display.monitor_id, id))
+            }
+            MonitorMatcher::Resolution { width, height } => {
+                if display.width == *width && height.map_or(true, |height| display.height == height) {
+                    return Ok(());
+                }
+                match height {
+                    Some(height) => Err(format!("resolution {}x{} is not {}x{}", display.width, display.height, width, height)),
+                    None => Err(format!("width {} is not {}", display.width, width)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorStrategy {
+    pub explicit: Option<MonitorMatcher>,
+    pub fallbacks: Vec<MonitorMatcher>,
+}
+impl MonitorStrategy {
+    pub fn new(explicit: Option<MonitorMatcher>, adapter_name: &str) -> Self {
+        Self {
+            explicit,
+            fallbacks: vec![
+                MonitorMatcher::AdapterName(String::from(adapter_name)),
+                MonitorMatcher::Resolution { width: DEFAULT_MONITOR_WIDTH, height: None },
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub display: DisplayDescriptor,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MonitorSelection {
+    pub selected: Vec<DisplayDescriptor>,
+    pub rejected: Vec<Rejection>,
+}
+impl MonitorSelection {
+    pub fn describe_failure(&self) -> String {
+        let mut msg = String::from("unable to locate the CloudCafe virtual monitor");
+        if self.rejected.is_empty() {
+            msg.push_str(", no displays were found");
+        }
+        for rejection in &self.rejected {
+            msg.push_str(&format!("\n{} ({}x{}): {}", rejection.display.device_name, rejection.display.width, rejection.display.height, rejection.reason));
+        }
+        msg
+    }
+}
+
+fn apply(displays: &[DisplayDescriptor], matcher: &MonitorMatcher, allow_primary: bool) -> MonitorSelection {
+    let mut selection = MonitorSelection::default();
+    for display in displays {
+        let result = match display.primary && !allow_primary {
+            true => Err(String::from("primary monitor is never used as the virtual monitor")),
+            false => matcher.check(display),
+        };
+        match result {
+            Ok(()) => selection.selected.push(display.clone()),
+            Err(reason) => selection.rejected.push(Rejection {
+                display: display.clone(),
+                reason,
+            }),
+        }
+    }
+    selection
+}
+
+pub fn select_monitors(displays: &[DisplayDescriptor], strategy: &MonitorStrategy) -> MonitorSelection {
+    if let Some(explicit) = strategy.explicit.as_ref() {
+        return apply(displays, explicit, true);
+    }
+    let mut rejected: Vec<Rejection> = displays.iter().map(|display| Rejection {
+        display: display.clone(),
+        reason: String::new(),
+    }).collect();
+    for matcher in &strategy.fallbacks {
+        let selection = apply(displays, matcher, false);
+        if !selection.selected.is_empty() {
+            return selection;
+        }
+        for (rejection, attempt) in rejected.iter_mut().zip(selection.rejected) {
+            if rejection.reason.contains(&attempt.reason) {
+                continue;
+            }
+            if !rejection.reason.is_empty() {
+                rejection.reason.push_str(", ");
+            }
+            rejection.reason.push_str(&attempt.reason);
+        }
+    }
+    MonitorSelection {
+        selected: vec![],
+        rejected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDD: &str = "IddSampleDriver Device";
+
+    fn display(device_name: &str, adapter_name: &str, width: u32, primary: bool) -> DisplayDescriptor {
+        DisplayDescriptor {
+            device_name: device_name.to_string(),
+            adapter_name: adapter_name.to_string(),
+            adapter_id: format!("PCI\\VEN_{adapter_name}"),
+            monitor_id: format!("MONITOR\\{device_name}"),
+            width,
+            height: 2000,
+            primary,
+            ..Default::default()
+        }
+    }
+    fn displays() -> Vec<DisplayDescriptor> {
+        vec![display("DISPLAY1", "NVIDIA", 4000, true), display("DISPLAY2", IDD, 1920, false), display("DISPLAY3", "AMD", 4000, false)]
+    }
+    fn selected(selection: &MonitorSelection) -> Vec<&str> {
+        selection.selected.iter().map(|display| display.device_name.as_str()).collect()
+    }
+
+    #[test]
+    fn explicit_matchers() {
+        let explicit = |matcher| select_monitors(&displays(), &MonitorStrategy::new(Some(matcher), IDD));
+        assert_eq!(selected(&explicit(MonitorMatcher::DeviceName(String::from("display3")))), vec!["DISPLAY3"]);
+        assert_eq!(selected(&explicit(MonitorMatcher::AdapterName(String::from("amd")))), vec!["DISPLAY3"]);
+        assert_eq!(selected(&explicit(MonitorMatcher::AdapterId(String::from("ven_iddsample")))), vec!["DISPLAY2"]);
+        assert_eq!(selected(&explicit(MonitorMatcher::MonitorId(String::from("display2")))), vec!["DISPLAY2"]);
+        assert_eq!(selected(&explicit(MonitorMatcher::Resolution { width: 4000, height: None })), vec!["DISPLAY1", "DISPLAY3"]);
+        assert_eq!(selected(&explicit(MonitorMatcher::Resolution { width: 1920, height: Some(1080) })), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn falls_back_to_the_idd_adapter_name() {
+        let selection = select_monitors(&displays(), &MonitorStrategy::new(None, IDD));
+        assert_eq!(selected(&selection), vec!["DISPLAY2"]);
+        assert_eq!(selection.rejected.len(), 2);
+        assert!(selection.rejected[0].reason.contains("primary"));
+    }
+
+    #[test]
+    fn falls_back_to_the_default_width_without_the_primary() {
+        let selection = select_monitors(&displays(), &MonitorStrategy::new(None, "Missing Adapter"));
+        assert_eq!(selected(&selection), vec!["DISPLAY3"]);
+    }
+
+    #[test]
+    fn no_match_explains_every_display() {
+        let selection = select_monitors(&displays()[..2], &MonitorStrategy::new(None, "Missing Adapter"));
+        assert!(selection.selected.is_empty());
+        assert_eq!(selection.rejected[0].reason, "primary monitor is never used as the virtual monitor");
+        assert_eq!(selection.rejected[1].reason, format!("adapter \"{IDD}\" is not \"Missing Adapter\", width 1920 is not {DEFAULT_MONITOR_WIDTH}"));
+        let failure = selection.describe_failure();
+        assert!(failure.starts_with("unable to locate the CloudCafe virtual monitor\nDISPLAY1 (4000x2000)"), "{failure}");
+        assert!(select_monitors(&[], &MonitorStrategy::new(None, IDD)).describe_failure().ends_with("no displays were found"));
+    }
+}
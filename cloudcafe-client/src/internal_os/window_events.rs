@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WindowSnapshot {
+    pub id: isize,
+    pub title: String,
+    pub width: i32,
+    pub height: i32,
+    pub visible: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WindowEvent {
+    Created { id: isize, title: String },
+    Destroyed(isize),
+    Shown(isize),
+    Hidden(isize),
+    TitleChanged { id: isize, title: String },
+    Resized { id: isize, width: i32, height: i32 },
+}
+impl WindowEvent {
+    pub fn id(&self) -> isize {
+        match self {
+            WindowEvent::Created { id, .. } => *id,
+            WindowEvent::Destroyed(id) => *id,
+            WindowEvent::Shown(id) => *id,
+            WindowEvent::Hidden(id) => *id,
+            WindowEvent::TitleChanged { id, .. } => *id,
+            WindowEvent::Resized { id, .. } => *id,
+        }
+    }
+}
+
+pub trait WindowEventSource {
+    fn wait(&mut self) -> Vec<WindowEvent>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WindowDiff {
+    known: HashMap<isize, WindowSnapshot>,
+}
+impl WindowDiff {
+    pub fn update(&mut self, id: isize, snapshot: Option<WindowSnapshot>) -> Vec<WindowEvent> {
+        let snapshot = match snapshot {
+            None => return match self.known.remove(&id) {
+                Some(_) => vec![WindowEvent::Destroyed(id)],
+                None => vec![],
+            },
+            Some(snapshot) => snapshot,
+        };
+        let mut events = Vec::new();
+        match self.known.get(&id) {
+            None => {
+                events.push(WindowEvent::Created { id, title: snapshot.title.clone() });
+                if snapshot.visible {
+                    events.push(WindowEvent::Shown(id));
+                }
+            }
+            Some(known) => {
+                if known.title != snapshot.title {
+                    events.push(WindowEvent::TitleChanged { id, title: snapshot.title.clone() });
+                }
+                if known.width != snapshot.width || known.height != snapshot.height {
+                    events.push(WindowEvent::Resized { id, width: snapshot.width, height: snapshot.height });
+                }
+                match (known.visible, snapshot.visible) {
+                    (false, true) => events.push(WindowEvent::Shown(id)),
+                    (true, false) => events.push(WindowEvent::Hidden(id)),
+                    _ => {}
+                }
+            }
+        }
+        self.known.insert(id, snapshot);
+        events
+    }
+    pub fn diff(&mut self, snapshots: Vec<WindowSnapshot>) -> Vec<WindowEvent> {
+        let mut gone = self.known.keys().copied().filter(|id| snapshots.iter().all(|snapshot| snapshot.id != *id)).collect::<Vec<_>>();
+        gone.sort();
+        let mut events = Vec::new();
+        for snapshot in snapshots {
+            events.extend(self.update(snapshot.id, Some(snapshot)));
+        }
+        for id in gone {
+            events.extend(self.update(id, None));
+        }
+        events
+    }
+}
+
+pub struct PollingSource<F: FnMut() -> Vec<WindowSnapshot>> {
+    snapshot: F,
+    interval: Duration,
+    diff: WindowDiff,
+}
+impl<F: FnMut() -> Vec<WindowSnapshot>> PollingSource<F> {
+    pub fn new(snapshot: F, interval: Duration) -> Self {
+        Self {
+            snapshot,
+            interval,
+            diff: WindowDiff::default(),
+        }
+    }
+    pub fn poll(&mut self) -> Vec<WindowEvent> {
+        self.diff.diff((self.snapshot)())
+    }
+}
+impl<F: FnMut() -> Vec<WindowSnapshot>> WindowEventSource for PollingSource<F> {
+    fn wait(&mut self) -> Vec<WindowEvent> {
+        let events = self.poll();
+        if events.is_empty() {
+            thread::sleep(self.interval);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: isize, title: &str, width: i32, visible: bool) -> WindowSnapshot {
+        WindowSnapshot {
+            id,
+            title: title.to_string(),
+            width,
+            height: 600,
+            visible,
+        }
+    }
+
+    #[test]
+    fn new_windows_are_created_then_shown() {
+        let mut diff = WindowDiff::default();
+        let events = diff.diff(vec![window(1, "a", 800, true), window(2, "", 800, false)]);
+        assert_eq!(events, vec![
+            WindowEvent::Created { id: 1, title: String::from("a") },
+            WindowEvent::Shown(1),
+            WindowEvent::Created { id: 2, title: String::new() },
+        ]);
+        assert_eq!(diff.diff(vec![window(1, "a", 800, true), window(2, "", 800, false)]), vec![]);
+    }
+
+    #[test]
+    fn visibility_changes() {
+        let mut diff = WindowDiff::default();
+        diff.diff(vec![window(1, "a", 800, false)]);
+        assert_eq!(diff.diff(vec![window(1, "a", 800, true)]), vec![WindowEvent::Shown(1)]);
+        assert_eq!(diff.diff(vec![window(1, "a", 800, false)]), vec![WindowEvent::Hidden(1)]);
+    }
+
+    #[test]
+    fn title_and_size_changes() {
+        let mut diff = WindowDiff::default();
+        diff.diff(vec![window(1, "a", 800, true)]);
+        assert_eq!(diff.diff(vec![window(1, "b", 800, true)]), vec![WindowEvent::TitleChanged { id: 1, title: String::from("b") }]);
+        assert_eq!(diff.diff(vec![window(1, "b", 1024, true)]), vec![WindowEvent::Resized { id: 1, width: 1024, height: 600 }]);
+        assert_eq!(diff.diff(vec![window(1, "c", 640, false)]), vec![
+            WindowEvent::TitleChanged { id: 1, title: String::from("c") },
+            WindowEvent::Resized { id: 1, width: 640, height: 600 },
+            WindowEvent::Hidden(1),
+        ]);
+    }
+
+    #[test]
+    fn missing_windows_are_destroyed() {
+        let mut diff = WindowDiff::default();
+        diff.diff(vec![window(3, "c", 800, true), window(1, "a", 800, true), window(2, "b", 800, true)]);
+        assert_eq!(diff.diff(vec![window(2, "b", 800, true)]), vec![WindowEvent::Destroyed(1), WindowEvent::Destroyed(3)]);
+        assert_eq!(diff.update(2, None), vec![WindowEvent::Destroyed(2)]);
+        assert_eq!(diff.update(2, None), vec![]);
+        assert_eq!(diff.diff(vec![window(1, "a", 800, false)]), vec![WindowEvent::Created { id: 1, title: String::from("a") }]);
+    }
+}
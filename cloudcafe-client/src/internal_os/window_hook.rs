@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+use std::thread;
+use std::time::Duration;
+use color_eyre::{Report, Result};
+use dxcapture::enumerate_windows;
+use windows::Win32::Foundation::{HINSTANCE, HWND};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::WindowsAndMessaging::{DispatchMessageW, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY, EVENT_OBJECT_HIDE, EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_SHOW, MSG, PeekMessageW, PM_REMOVE, TranslateMessage, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS};
+use crate::internal_os::window_events::{PollingSource, WindowDiff, WindowEvent, WindowEventSource, WindowSnapshot};
+use crate::windows_bindings::{get_window_rect, Hwnd, is_top_level, is_window, window_title, window_visible};
+
+const OBJID_WINDOW: i32 = 0;
+const CHILDID_SELF: i32 = 0;
+const PUMP_INTERVAL: Duration = Duration::from_millis(16);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+thread_local! {
+    static RAW_EVENTS: RefCell<Vec<(u32, isize)>> = RefCell::new(Vec::new());
+}
+
+unsafe extern "system" fn win_event_proc(_hook: HWINEVENTHOOK, event: u32, hwnd: HWND, id_object: i32, id_child: i32, _thread: u32, _time: u32) {
+    if hwnd.0 == 0 || id_object != OBJID_WINDOW || id_child != CHILDID_SELF {
+        return;
+    }
+    RAW_EVENTS.with(|raw| raw.borrow_mut().push((event, hwnd.0)));
+}
+
+pub fn snapshot_window(hwnd: Hwnd) -> Option<WindowSnapshot> {
+    if !is_window(hwnd) || !is_top_level(hwnd) {
+        return None;
+    }
+    let title = window_title(hwnd).unwrap_or_default();
+    let rect = get_window_rect(hwnd);
+    Some(WindowSnapshot {
+        id: hwnd.0,
+        title,
+        width: rect.right - rect.left,
+        height: rect.bottom - rect.top,
+        visible: window_visible(hwnd),
+    })
+}
+fn enumerate_snapshots() -> Vec<WindowSnapshot> {
+    enumerate_windows().into_iter().filter_map(|window_info| snapshot_window(HWND(window_info.handle as isize))).collect()
+}
+
+pub struct WinEventSource {
+    hook: HWINEVENTHOOK,
+    diff: WindowDiff,
+    pending: Vec<WindowEvent>,
+}
+impl WinEventSource {
+    pub fn new() -> Result<Self> {
+        let hook = unsafe {
+            SetWinEventHook(EVENT_OBJECT_CREATE, EVENT_OBJECT_NAMECHANGE, HINSTANCE(0), Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS)
+        };
+        if hook.0 == 0 {
+            return Err(Report::msg("unable to install window event hook"));
+        }
+        let mut diff = WindowDiff::default();
+        let pending = diff.diff(enumerate_snapshots());
+        Ok(Self {
+            hook,
+            diff,
+            pending,
+        })
+    }
+    fn pump_messages() {
+        let mut msg = MSG::default();
+        unsafe {
+            while PeekMessageW(&mut msg, HWND(0), 0, 0, PM_REMOVE).as_bool() {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+}
+impl WindowEventSource for WinEventSource {
+    fn wait(&mut self) -> Vec<WindowEvent> {
+        let mut events = std::mem::take(&mut self.pending);
+        Self::pump_messages();
+        let mut raw = RAW_EVENTS.with(|raw| raw.take());
+        raw.dedup();
+        for (event, id) in raw {
+            let snapshot = match event {
+                EVENT_OBJECT_DESTROY => None,
+                EVENT_OBJECT_CREATE | EVENT_OBJECT_SHOW | EVENT_OBJECT_HIDE | EVENT_OBJECT_LOCATIONCHANGE | EVENT_OBJECT_NAMECHANGE => snapshot_window(HWND(id)),
+                _ => continue,
+            };
+            events.extend(self.diff.update(id, snapshot));
+        }
+        if events.is_empty() {
+            thread::sleep(PUMP_INTERVAL);
+        }
+        events
+    }
+}
+impl Drop for WinEventSource {
+    fn drop(&mut self) {
+        unsafe {
+            UnhookWinEvent(self.hook);
+        }
+    }
+}
+
+pub fn window_source() -> Box<dyn WindowEventSource> {
+    match WinEventSource::new() {
+        Ok(source) => Box::new(source),
+        Err(err) => {
+            println!("{err}, falling back to polling windows");
+            Box::new(PollingSource::new(enumerate_snapshots, POLL_INTERVAL))
+        }
+    }
+}
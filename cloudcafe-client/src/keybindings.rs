@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use color_eyre::{Report, Result};
+use serde::{Deserialize, Serialize};
+use crate::input::{HookEvent, Key, Modifiers, TimedEvent};
+
+// a multi-chord sequence is abandoned when the next chord takes longer than this
+const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InputMode {
+    Desktop,
+    RunMenu,
+    WindowCaptured,
+}
+impl InputMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputMode::Desktop => "desktop",
+            InputMode::RunMenu => "run_menu",
+            InputMode::WindowCaptured => "window_captured",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    OpenRunMenu,
+    CloseRunMenu,
+    SelectPrevious,
+    SelectNext,
+    Launch,
+    WorkspacePrevious,
+    WorkspaceNext,
+    MoveToWorkspacePrevious,
+    MoveToWorkspaceNext,
+    RadiusIncrease,
+    RadiusDecrease,
+    Recenter,
+}
+impl Action {
+    pub const ALL: [Action; 13] = [
+        Action::Quit, Action::OpenRunMenu, Action::CloseRunMenu, Action::SelectPrevious, Action::SelectNext, Action::Launch,
+        Action::WorkspacePrevious, Action::WorkspaceNext, Action::MoveToWorkspacePrevious, Action::MoveToWorkspaceNext,
+        Action::RadiusIncrease, Action::RadiusDecrease, Action::Recenter,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::OpenRunMenu => "open_run_menu",
+            Action::CloseRunMenu => "close_run_menu",
+            Action::SelectPrevious => "select_previous",
+            Action::SelectNext => "select_next",
+            Action::Launch => "launch",
+            Action::WorkspacePrevious => "workspace_previous",
+            Action::WorkspaceNext => "workspace_next",
+            Action::MoveToWorkspacePrevious => "move_to_workspace_previous",
+            Action::MoveToWorkspaceNext => "move_to_workspace_next",
+            Action::RadiusIncrease => "radius_increase",
+            Action::RadiusDecrease => "radius_decrease",
+            Action::Recenter => "recenter",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.name() == name)
+    }
+}
+
+fn add_modifier(modifiers: &mut Modifiers, key: Key) -> bool {
+    match key {
+        Key::Windows => modifiers.windows = true,
+        Key::Control => modifiers.control = true,
+        Key::Alt => modifiers.alt = true,
+        Key::Shift => modifiers.shift = true,
+        _ => return false,
+    }
+    true
+}
+
+pub fn is_modifier(key: Key) -> bool {
+    matches!(key, Key::Windows | Key::Control | Key::Alt | Key::Shift)
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    let alias = match name.to_ascii_lowercase().as_str() {
+        "win" | "super" => Some(Key::Windows),
+        "ctrl" => Some(Key::Control),
+        "left" => Some(Key::ArrowLeft),
+        "right" => Some(Key::ArrowRight),
+        "up" => Some(Key::ArrowUp),
+        "down" => Some(Key::ArrowDown),
+        "esc" => Some(Key::Escape),
+        "return" => Some(Key::Enter),
+        _ => None,
+    };
+    alias.or_else(|| match name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()) {
+        true => Key::ALL.iter().copied().find(|key| format!("{:?}", key) == format!("Digit{name}")),
+        false => Key::ALL.iter().copied().find(|key| format!("{:?}", key).eq_ignore_ascii_case(name)),
+    })
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+impl Chord {
+    pub fn parse(text: &str) -> Result<Self> {
+        let parts = text.split('+').map(|part| part.trim()).collect::<Vec<_>>();
+        let (key, held) = parts.split_last().ok_or(Report::msg("empty key chord"))?;
+        let mut modifiers = Modifiers::default();
+        for part in held {
+            let modifier = parse_key(part).ok_or(Report::msg(format!("unknown key \"{part}\" in \"{text}\"")))?;
+            if !add_modifier(&mut modifiers, modifier) {
+                return Err(Report::msg(format!("\"{part}\" in \"{text}\" is not a modifier")));
+            }
+        }
+        let key = parse_key(key).ok_or(Report::msg(format!("unknown key \"{key}\" in \"{text}\"")))?;
+        if is_modifier(key) {
+            return Err(Report::msg(format!("\"{text}\" has no key besides modifiers")));
+        }
+        Ok(Self { modifiers, key })
+    }
+}
+impl Display for Chord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (held, name) in [(self.modifiers.windows, "Win"), (self.modifiers.control, "Ctrl"), (self.modifiers.alt, "Alt"), (self.modifiers.shift, "Shift")] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+pub fn parse_sequence(text: &str) -> Result<Vec<Chord>> {
+    let sequence = text.split_whitespace().map(Chord::parse).collect::<Result<Vec<_>>>()?;
+    if sequence.is_empty() {
+        return Err(Report::msg("empty key binding"));
+    }
+    Ok(sequence)
+}
+fn sequence_name(sequence: &[Chord]) -> String {
+    sequence.iter().map(|chord| chord.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeybindingConfig {
+    pub global: BTreeMap<String, String>,
+    pub desktop: BTreeMap<String, String>,
+    pub run_menu: BTreeMap<String, String>,
+    pub window_captured: BTreeMap<String, String>,
+}
+
+fn default_bindings(mode: Option<InputMode>) -> Vec<(Action, &'static str)> {
+    let desktop = vec![
+        (Action::WorkspacePrevious, "Win+Alt+Left"),
+        (Action::WorkspaceNext, "Win+Alt+Right"),
+        (Action::MoveToWorkspacePrevious, "Win+Alt+Shift+Left"),
+        (Action::MoveToWorkspaceNext, "Win+Alt+Shift+Right"),
+        (Action::RadiusIncrease, "Win+Alt+Up"),
+        (Action::RadiusDecrease, "Win+Alt+Down"),
+        (Action::Recenter, "Win+Alt+C"),
+    ];
+    match mode {
+        None => vec![(Action::Quit, "Win+Q")],
+        Some(InputMode::Desktop) => [desktop, vec![(Action::OpenRunMenu, "Win+O")]].concat(),
+        Some(InputMode::WindowCaptured) => desktop,
+        Some(InputMode::RunMenu) => vec![
+            (Action::CloseRunMenu, "Escape"),
+            (Action::SelectPrevious, "Up"),
+            (Action::SelectNext, "Down"),
+            (Action::Launch, "Enter"),
+        ],
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Binding {
+    sequence: Vec<Chord>,
+    action: Action,
+    mode: Option<InputMode>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+impl Keymap {
+    pub fn new(config: &KeybindingConfig) -> Result<Self> {
+        let mut bindings = Vec::new();
+        let sections = [(None, &config.global), (Some(InputMode::Desktop), &config.desktop), (Some(InputMode::RunMenu), &config.run_menu), (Some(InputMode::WindowCaptured), &config.window_captured)];
+        for (mode, user) in sections {
+            let section = mode.map_or("global", |mode: InputMode| mode.name());
+            let mut merged = default_bindings(mode).into_iter().map(|(action, text)| (action, text.to_string())).collect::<Vec<_>>();
+            for (name, text) in user {
+                let action = Action::from_name(name).ok_or(Report::msg(format!("unknown action \"{name}\" in [keybindings.{section}]")))?;
+                merged.retain(|(bound, _)| *bound != action);
+                merged.push((action, text.clone()));
+            }
+            for (action, text) in merged {
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let sequence = parse_sequence(&text).map_err(|err| Report::msg(format!("invalid binding for {} in [keybindings.{section}]: {err}", action.name())))?;
+                bindings.push(Binding { sequence, action, mode });
+            }
+        }
+        let keymap = Self { bindings };
+        let conflicts = keymap.conflicts();
+        if !conflicts.is_empty() {
+            return Err(Report::msg(format!("conflicting keybindings:\n{}", conflicts.join("\n"))));
+        }
+        Ok(keymap)
+    }
+    fn overlaps(a: &Binding, b: &Binding) -> bool {
+        a.mode.is_none() || b.mode.is_none() || a.mode == b.mode
+    }
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (index, a) in self.bindings.iter().enumerate() {
+            for b in self.bindings.iter().skip(index + 1).filter(|b| Self::overlaps(a, b)) {
+                let (short, long) = match a.sequence.len() <= b.sequence.len() {
+                    true => (a, b),
+                    false => (b, a),
+                };
+                if !long.sequence.starts_with(&short.sequence) {
+                    continue;
+                }
+                let section = |binding: &Binding| binding.mode.map_or("global", |mode| mode.name());
+                conflicts.push(match short.sequence.len() == long.sequence.len() {
+                    true => format!("{} is bound to both {} ({}) and {} ({})", sequence_name(&short.sequence), short.action.name(), section(short), long.action.name(), section(long)),
+                    false => format!("{} for {} ({}) shadows {} for {} ({})", sequence_name(&short.sequence), short.action.name(), section(short), sequence_name(&long.sequence), long.action.name(), section(long)),
+                });
+            }
+        }
+        conflicts
+    }
+    fn active(&self, mode: InputMode) -> impl Iterator<Item = &Binding> {
+        self.bindings.iter().filter(move |binding| binding.mode.map_or(true, |bound| bound == mode))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyDispatcher {
+    pending: Vec<Chord>,
+    mode: Option<InputMode>,
+    last: Option<Instant>,
+}
+impl KeyDispatcher {
+    pub fn press(&mut self, keymap: &Keymap, mode: InputMode, chord: Chord, time: Instant) -> Option<Action> {
+        let expired = self.last.is_some_and(|last| time.saturating_duration_since(last) > SEQUENCE_TIMEOUT);
+        if self.mode != Some(mode) || expired {
+            self.mode = Some(mode);
+            self.pending.clear();
+        }
+        self.last = Some(time);
+        self.pending.push(chord);
+        loop {
+            if let Some(binding) = keymap.active(mode).find(|binding| binding.sequence == self.pending) {
+                self.pending.clear();
+                return Some(binding.action);
+            }
+            if keymap.active(mode).any(|binding| binding.sequence.starts_with(&self.pending)) {
+                return None;
+            }
+            if self.pending.len() == 1 {
+                self.pending.clear();
+                return None;
+            }
+            self.pending.drain(..self.pending.len() - 1);
+        }
+    }
+    pub fn dispatch(&mut self, keymap: &Keymap, mode: InputMode, events: &[TimedEvent]) -> Vec<Action> {
+        let mut actions = Vec::new();
+        for event in events {
+            if let HookEvent::KeyDown(key) = event.event {
+                if !is_modifier(key) {
+                    actions.extend(self.press(keymap, mode, Chord { modifiers: event.modifiers, key }, event.time));
+                }
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(desktop: &[(&str, &str)]) -> Keymap {
+        let config = KeybindingConfig {
+            desktop: desktop.iter().map(|(action, text)| (action.to_string(), text.to_string())).collect(),
+            ..KeybindingConfig::default()
+        };
+        Keymap::new(&config).unwrap()
+    }
+    fn chord(text: &str) -> Chord {
+        Chord::parse(text).unwrap()
+    }
+    fn key_down(text: &str, time: Instant) -> TimedEvent {
+        let chord = chord(text);
+        TimedEvent {
+            time,
+            modifiers: chord.modifiers,
+            event: HookEvent::KeyDown(chord.key),
+        }
+    }
+
+    #[test]
+    fn parses_chords_case_insensitively() {
+        let expected = Chord { modifiers: Modifiers { windows: true, shift: true, ..Modifiers::default() }, key: Key::ArrowLeft };
+        assert_eq!(chord("Win+Shift+Left"), expected);
+        assert_eq!(chord("win + SHIFT + left"), expected);
+        assert_eq!(chord("ctrl+alt+7"), Chord { modifiers: Modifiers { control: true, alt: true, ..Modifiers::default() }, key: Key::Digit7 });
+        assert_eq!(parse_sequence("Win+K  c").unwrap(), vec![chord("Win+K"), chord("C")]);
+    }
+
+    #[test]
+    fn rejects_bad_chords() {
+        assert!(Chord::parse("Win+Nope").unwrap_err().to_string().contains("unknown key \"Nope\""));
+        assert!(Chord::parse("Hyper+A").unwrap_err().to_string().contains("unknown key \"Hyper\""));
+        assert!(Chord::parse("A+B").unwrap_err().to_string().contains("is not a modifier"));
+        assert!(Chord::parse("Win+Shift").unwrap_err().to_string().contains("no key besides modifiers"));
+        assert!(parse_sequence("  ").is_err());
+        let config = KeybindingConfig {
+            desktop: [(String::from("recenter"), String::from("Win+Nope"))].into(),
+            ..KeybindingConfig::default()
+        };
+        assert!(Keymap::new(&config).unwrap_err().to_string().contains("invalid binding for recenter in [keybindings.desktop]"));
+    }
+
+    #[test]
+    fn reports_duplicate_chords_in_the_same_mode() {
+        let config = KeybindingConfig {
+            desktop: [(String::from("recenter"), String::from("Win+Alt+Left"))].into(),
+            ..KeybindingConfig::default()
+        };
+        let err = Keymap::new(&config).unwrap_err().to_string();
+        assert!(err.contains("Win+Alt+ArrowLeft is bound to both workspace_previous (desktop) and recenter (desktop)"), "{err}");
+        let config = KeybindingConfig {
+            desktop: [(String::from("recenter"), String::from("Win+Q"))].into(),
+            ..KeybindingConfig::default()
+        };
+        let err = Keymap::new(&config).unwrap_err().to_string();
+        assert!(err.contains("Win+Q is bound to both quit (global) and recenter (desktop)"), "{err}");
+    }
+
+    #[test]
+    fn same_chord_in_different_modes_is_fine() {
+        let keymap = keymap(&[("open_run_menu", "Up")]);
+        assert!(keymap.conflicts().is_empty());
+        let mut dispatcher = KeyDispatcher::default();
+        let now = Instant::now();
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("Up"), now), Some(Action::OpenRunMenu));
+        assert_eq!(dispatcher.press(&keymap, InputMode::RunMenu, chord("Up"), now), Some(Action::SelectPrevious));
+        assert_eq!(dispatcher.press(&keymap, InputMode::WindowCaptured, chord("Up"), now), None);
+        assert_eq!(dispatcher.press(&keymap, InputMode::RunMenu, chord("Win+Q"), now), Some(Action::Quit));
+    }
+
+    #[test]
+    fn reports_prefixes_that_shadow_sequences() {
+        let config = KeybindingConfig {
+            desktop: [(String::from("recenter"), String::from("Win+O C"))].into(),
+            ..KeybindingConfig::default()
+        };
+        let err = Keymap::new(&config).unwrap_err().to_string();
+        assert!(err.contains("Win+O for open_run_menu (desktop) shadows Win+O C for recenter (desktop)"), "{err}");
+        let config = KeybindingConfig {
+            window_captured: [(String::from("recenter"), String::from("Win+O C"))].into(),
+            ..KeybindingConfig::default()
+        };
+        assert!(Keymap::new(&config).is_ok());
+    }
+
+    #[test]
+    fn sequences_complete_within_the_timeout() {
+        let keymap = keymap(&[("recenter", "Win+K C")]);
+        let mut dispatcher = KeyDispatcher::default();
+        let start = Instant::now();
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("Win+K"), start), None);
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("C"), start + SEQUENCE_TIMEOUT), Some(Action::Recenter));
+    }
+
+    #[test]
+    fn pending_sequence_expires() {
+        let keymap = keymap(&[("recenter", "Win+K C")]);
+        let mut dispatcher = KeyDispatcher::default();
+        let start = Instant::now();
+        let late = start + SEQUENCE_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("Win+K"), start), None);
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("C"), late), None);
+        assert!(dispatcher.pending.is_empty());
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("Win+K"), late), None);
+        assert_eq!(dispatcher.press(&keymap, InputMode::Desktop, chord("C"), late + Duration::from_millis(10)), Some(Action::Recenter));
+    }
+
+    #[test]
+    fn dispatch_uses_event_times() {
+        let keymap = keymap(&[("recenter", "Win+K C")]);
+        let mut dispatcher = KeyDispatcher::default();
+        let start = Instant::now();
+        let events = [key_down("Win+K", start), key_down("C", start + Duration::from_millis(100)), key_down("Win+K", start + Duration::from_secs(1)), key_down("C", start + Duration::from_secs(5))];
+        assert_eq!(dispatcher.dispatch(&keymap, InputMode::Desktop, &events), vec![Action::Recenter]);
+        assert!(dispatcher.pending.is_empty());
+        assert_eq!(dispatcher.dispatch(&keymap, InputMode::Desktop, &[key_down("Win+Alt+Left", start + Duration::from_secs(60))]), vec![Action::WorkspacePrevious]);
+    }
+}
@@ -8,6 +8,9 @@ mod virtual_manager;
 mod internal_os;
 mod run_menu;
 mod gamma_shader;
+mod config;
+mod worker;
+mod keybindings;
 
 use std::{env, fs};
 use std::ffi::c_int;
@@ -30,9 +33,11 @@ use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::UI::Input::KeyboardAndMouse::{VIRTUAL_KEY, VK_LWIN, VK_RWIN};
 use windows::Win32::UI::WindowsAndMessaging::{CallNextHookEx, DispatchMessageW, GetMessageW, HC_ACTION, HHOOK, KBDLLHOOKSTRUCT, SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx, WH_KEYBOARD_LL, WM_KEYDOWN};
 use crate::asset_loader::load_assets;
-use crate::input::{Key, KeyboardMouseState};
+use crate::config::UserConfig;
+use crate::input::KeyboardMouseState;
 use crate::internal_os::internal_mouse::IMouse;
 use crate::internal_os::internal_window::IWindow;
+use crate::keybindings::{Action, InputMode, KeyDispatcher, Keymap};
 use crate::run_menu::RunMenu;
 use crate::sk_env::SkEnv;
 use crate::values::IVec2;
@@ -52,29 +57,62 @@ fn main() {
     }
 }
 fn main2() -> Result<()> {
-    let console_hwnd = service::init()?.ok_or(Report::msg("no console hwnd"))?;
+    let config = UserConfig::load()?;
+    let service = service::init(&config.driver_name)?.ok_or(Report::msg("no console hwnd"))?;
+    let console_hwnd = service.console_hwnd;
+    match service.driver_status() {
+        Ok((state, sessions)) => println!("virtual display driver is {:?}, {} client session(s)", state, sessions),
+        Err(err) => println!("unable to query the virtual display driver: {err}"),
+    }
+    if let Some([width, height]) = config.resolution {
+        if let Err(err) = service.set_resolution(width, height) {
+            println!("unable to set the virtual monitor resolution to {width}x{height}: {err}");
+        }
+    }
+    let keymap = Keymap::new(&config.keybindings)?;
+    let mut key_dispatcher = KeyDispatcher::default();
     load_assets();
     let mut radius = 1.3;
     let sk = Settings::default().display_preference(DisplayMode::MixedReality).no_flatscreen_fallback(true).render_scaling(2.0).app_name("Cloudcafe XR Desktop").disable_unfocused_sleep(true).init()?;
     let sk_env = SkEnv::new(&sk)?;
     let mut internal_mouse = IMouse::new(IVec2::from([300, 300]));
-    //let mut run_menu = RunMenu::new(&sk)?;
+    let mut run_menu = RunMenu::new(&sk)?;
     let mut keyboard_mouse = KeyboardMouseState::new();
-    let mut virtual_desktop = VDesktop::new(&sk, console_hwnd, radius)?;
+    let mut virtual_desktop = VDesktop::new(&sk, &config, console_hwnd, radius)?;
     println!("virtual desktop created");
     internal_mouse.tick();
+    let mut service_error = None;
     sk.run(|sk| {
+        service.tick();
+        if let Some(err) = service.take_heartbeat_error() {
+            service_error.replace(err);
+            sk.quit();
+        }
         internal_mouse.tick();
         sk_env.draw(sk, radius, &mut virtual_desktop, &mut internal_mouse);
-        //run_menu.draw(sk, &mut keyboard_mouse, radius);
-        if keyboard_mouse.get_input(Key::Windows).active {
-            if keyboard_mouse.get_input(Key::Q).active {
-                sk.quit();
-            }
+        let mode = match virtual_desktop.has_captured_window() {
+            true => InputMode::WindowCaptured,
+            false => InputMode::Desktop,
+        };
+        let mode = if run_menu.is_open() { InputMode::RunMenu } else { mode };
+        let events = keyboard_mouse.drain_events();
+        let actions = key_dispatcher.dispatch(&keymap, mode, &events);
+        run_menu.draw(sk, &events, &actions, radius);
+        if actions.contains(&Action::Quit) {
+            sk.quit();
         }
-        virtual_desktop.draw(sk, &mut internal_mouse, &mut keyboard_mouse, &mut radius);
+        virtual_desktop.draw(sk, &mut internal_mouse, &mut keyboard_mouse, &events, &actions, &mut radius);
         keyboard_mouse.reset_active();
     }, |_| {});
+    virtual_desktop.save_layout();
+    if let Err(err) = service.shutdown() {
+        println!("unable to shut down the elevated service: {err}");
+    }
+    drop(virtual_desktop);
+    drop(keyboard_mouse);
+    if let Some(err) = service_error {
+        return Err(Report::msg(format!("Lost the connection to the CloudCafe elevated service: {err}")));
+    }
     Ok(())
 }
 
@@ -19,7 +19,8 @@ use stereokit::render::RenderLayer;
 use stereokit::text::TextStyle;
 use stereokit::ui::{MoveType, window, WindowType};
 use stereokit::values::Color128;
-use crate::input::{Key, KeyboardMouseState};
+use crate::input::{HookEvent, Key, TimedEvent};
+use crate::keybindings::Action;
 use crate::values::{quat_lookat, sphere_2_cart};
 
 pub struct RunMenu {
@@ -36,8 +37,6 @@ pub struct RunMenu {
     selected_option: Option<usize>,
 }
 
-pub const ALPHABET: [Key; 26] = [Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z];
-
 impl RunMenu {
     pub fn new(sk: &impl StereoKitContext) -> Result<Self> {
         let selected_option_mesh = Mesh::gen_plane(sk, [0.5, 0.5], Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 1)?;
@@ -67,7 +66,10 @@ impl RunMenu {
             selected_option: None,
         })
     }
-    pub fn draw(&mut self, sk: &StereoKitDraw, keyboard_mouse: &mut KeyboardMouseState, radius: f32) {
+    pub fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+    pub fn draw(&mut self, sk: &StereoKitDraw, events: &[TimedEvent], actions: &[Action], radius: f32) {
         //self.pose.position = sphere_2_cart(Vec3::new(radius - 0.05, (PI / 2.0) + (PI / 16.0), -PI / 4.0)).into();
         window(sk, "", &mut self.pose, Vec2::new(0.5, 0.5).into(), WindowType::WindowBody, MoveType::MoveNone, |ui| {
             if let Some(input) = self.input.as_ref() {
@@ -89,29 +91,33 @@ impl RunMenu {
                 });
             }
         });
-        if keyboard_mouse.get_input(Key::Windows).active {
-            if keyboard_mouse.get_input(Key::O).active {
-                self.input = Some(String::new());
-                if self.entries.len() > 0 {
-                    self.selected_option = Some(0);
-                }
-                return;
+        if actions.contains(&Action::OpenRunMenu) {
+            self.input = Some(String::new());
+            if self.entries.len() > 0 {
+                self.selected_option = Some(0);
             }
+            return;
+        }
+        if actions.contains(&Action::CloseRunMenu) {
+            self.input.take();
         }
 
         let mut input_changed = false;
-        if keyboard_mouse.get_input(Key::MouseLeft).just_changed || keyboard_mouse.get_input(Key::MouseRight).just_changed {
+        if events.iter().any(|event| matches!(event.event, HookEvent::MouseButton { button: Key::MouseLeft | Key::MouseRight, .. })) {
             self.input.take();
         }
         if let Some(input) = self.input.as_mut() {
-            if keyboard_mouse.get_input(Key::Backspace).just_changed && keyboard_mouse.get_input(Key::Backspace).active {
-                input.pop();
-                input_changed = true;
-            }
-            for key in ALPHABET {
-                if keyboard_mouse.get_input(key).just_changed && keyboard_mouse.get_input(key).active {
-                    input_changed = true;
-                    input.push_str(key.as_str());
+            for event in events {
+                match event.event {
+                    HookEvent::KeyDown(Key::Backspace) => {
+                        input.pop();
+                        input_changed = true;
+                    }
+                    HookEvent::Char(c) if !event.modifiers.windows && !event.modifiers.control && !event.modifiers.alt => {
+                        input.push(c.to_ascii_lowercase());
+                        input_changed = true;
+                    }
+                    _ => {}
                 }
             }
             let mut entries = Vec::new();
@@ -135,7 +141,7 @@ impl RunMenu {
                     }
                 }
             }
-            if keyboard_mouse.get_input(Key::Enter).active {
+            if actions.contains(&Action::Launch) {
                 if let Some(selected) = self.selected_option.take() {
                     let path_to_run = entries.get(selected).unwrap().path();
                     println!("running: {:?}", path_to_run);
@@ -143,7 +149,7 @@ impl RunMenu {
                     self.input.take();
                 }
             }
-            if keyboard_mouse.get_input(Key::ArrowUp).just_changed && keyboard_mouse.get_input(Key::ArrowUp).active {
+            if actions.contains(&Action::SelectPrevious) {
                 if let Some(selected) = self.selected_option.take() {
                     if selected == 0 {
                         self.selected_option.replace(0);
@@ -152,7 +158,7 @@ impl RunMenu {
                     }
                 }
             }
-            if keyboard_mouse.get_input(Key::ArrowDown).just_changed && keyboard_mouse.get_input(Key::ArrowDown).active {
+            if actions.contains(&Action::SelectNext) {
                 if let Some(selected) = self.selected_option.take() {
                     if selected == entries.len() - 1 {
                         self.selected_option.replace(selected);
@@ -0,0 +1,105 @@
+use std::net::TcpStream;
+use std::process::Child;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use cloudcafe_common::codec::{read_message, write_message};
+use cloudcafe_common::service::{ClientToServiceMsg, DriverState, ServiceCapabilities, ServiceToClientMsg};
+use color_eyre::{Report, Result};
+use crate::service::HEARTBEAT_INTERVAL;
+use crate::windows_bindings::Hwnd;
+use crate::worker::{RestartPolicy, Worker};
+
+const HANG_THRESHOLD: Duration = Duration::from_secs(5);
+
+pub struct ServiceConnection {
+    stream: Arc<Mutex<TcpStream>>,
+    pub console_hwnd: Hwnd,
+    pub capabilities: ServiceCapabilities,
+    #[allow(dead_code)]
+    mouse_process: Child,
+    frames: Arc<AtomicU64>,
+    heartbeat_error: Arc<Mutex<Option<String>>>,
+    _heartbeat: Option<Worker>,
+}
+
+fn request(stream: &Mutex<TcpStream>, msg: ClientToServiceMsg) -> Result<ServiceToClientMsg> {
+    let stream = stream.lock().unwrap_or_else(PoisonError::into_inner);
+    write_message(&*stream, &msg)?;
+    match read_message(&*stream)? {
+        ServiceToClientMsg::Failed(err) => Err(Report::msg(err)),
+        reply => Ok(reply),
+    }
+}
+
+impl ServiceConnection {
+    pub fn new(stream: TcpStream, console_hwnd: Hwnd, capabilities: ServiceCapabilities, mouse_process: Child) -> Self {
+        let stream = Arc::new(Mutex::new(stream));
+        let frames = Arc::new(AtomicU64::new(0));
+        let heartbeat_error = Arc::new(Mutex::new(None));
+        let heartbeat = capabilities.contains(ServiceCapabilities::HEARTBEAT).then(|| {
+            let stream = stream.clone();
+            let frames = frames.clone();
+            let heartbeat_error = heartbeat_error.clone();
+            Worker::spawn("heartbeat", RestartPolicy::Never, move |cancel| {
+                let mut last_frame = frames.load(Ordering::SeqCst);
+                let mut last_progress = Instant::now();
+                let mut reported_hang = false;
+                while cancel.sleep(HEARTBEAT_INTERVAL) {
+                    if let Err(err) = request(&stream, ClientToServiceMsg::Heartbeat) {
+                        println!("heartbeat failed: {err}");
+                        heartbeat_error.lock().unwrap_or_else(PoisonError::into_inner).replace(err.to_string());
+                        break;
+                    }
+                    let frame = frames.load(Ordering::SeqCst);
+                    if frame != last_frame {
+                        if reported_hang {
+                            println!("frame loop recovered after {:.1}s", last_progress.elapsed().as_secs_f32());
+                        }
+                        last_frame = frame;
+                        last_progress = Instant::now();
+                        reported_hang = false;
+                    } else if !reported_hang && last_progress.elapsed() >= HANG_THRESHOLD {
+                        println!("frame loop has not ticked for {:.1}s", last_progress.elapsed().as_secs_f32());
+                        reported_hang = true;
+                    }
+                }
+            })
+        });
+        Self {
+            stream,
+            console_hwnd,
+            capabilities,
+            mouse_process,
+            frames,
+            heartbeat_error,
+            _heartbeat: heartbeat,
+        }
+    }
+    pub fn tick(&self) {
+        self.frames.fetch_add(1, Ordering::SeqCst);
+    }
+    pub fn take_heartbeat_error(&self) -> Option<String> {
+        self.heartbeat_error.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+    pub fn request(&self, msg: ClientToServiceMsg) -> Result<ServiceToClientMsg> {
+        request(&self.stream, msg)
+    }
+    pub fn driver_status(&self) -> Result<(DriverState, u32)> {
+        match self.request(ClientToServiceMsg::QueryDriverStatus)? {
+            ServiceToClientMsg::DriverStatus { state, sessions } => Ok((state, sessions)),
+            reply => Err(Report::msg(format!("unexpected reply to driver status query: {:?}", reply))),
+        }
+    }
+    pub fn set_resolution(&self, width: u32, height: u32) -> Result<()> {
+        if !self.capabilities.contains(ServiceCapabilities::SET_RESOLUTION) {
+            return Err(Report::msg("elevated service can't change the virtual monitor resolution"));
+        }
+        self.request(ClientToServiceMsg::SetResolution { width, height })?;
+        Ok(())
+    }
+    pub fn shutdown(&self) -> Result<()> {
+        self.request(ClientToServiceMsg::Shutdown)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt::{Display, Formatter};
+use cloudcafe_common::service::DriverState;
+use crate::service::powershell_scripts::ScriptType;
+
+pub const DEFAULT_FRIENDLY_NAME: &'static str = "IddSampleDriver Device";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DriverError {
+    DeviceNotFound(String),
+    AccessDenied,
+    RebootRequired,
+    ScriptFailed(String),
+    Io(String),
+}
+impl Display for DriverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriverError::DeviceNotFound(name) => write!(f, "no display device named \"{name}\" was found, is the CloudCafe driver installed?"),
+            DriverError::AccessDenied => write!(f, "access denied while changing the display driver, the service must run elevated"),
+            DriverError::RebootRequired => write!(f, "Windows needs to restart before the display driver can be changed"),
+            DriverError::ScriptFailed(err) => write!(f, "display driver script failed: {err}"),
+            DriverError::Io(err) => write!(f, "unable to run powershell: {err}"),
+        }
+    }
+}
+impl std::error::Error for DriverError {}
+
+pub trait DisplayDriverControl {
+    fn enable(&mut self) -> Result<(), DriverError>;
+    fn disable(&mut self) -> Result<(), DriverError>;
+    fn status(&mut self) -> Result<DriverState, DriverError>;
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<(), DriverError>;
+}
+
+pub fn parse_status(stdout: &str) -> DriverState {
+    let status = match stdout.lines().map(str::trim).find(|line| !line.is_empty()) {
+        None => return DriverState::NotPresent,
+        Some(status) => status,
+    };
+    match status.to_ascii_lowercase().as_str() {
+        "ok" | "degraded" => DriverState::Enabled,
+        "error" => DriverState::Disabled,
+        "unknown" => DriverState::NotPresent,
+        _ => DriverState::Unknown,
+    }
+}
+
+pub fn parse_error(stderr: &str) -> DriverError {
+    let lower = stderr.to_ascii_lowercase();
+    if lower.contains("no matching win32_pnpentity") || lower.contains("objectnotfound") {
+        let name = stderr.split("LIKE '").nth(1).and_then(|rest| rest.split('\'').next()).unwrap_or(DEFAULT_FRIENDLY_NAME);
+        return DriverError::DeviceNotFound(name.to_string());
+    }
+    if lower.contains("access denied") || lower.contains("access is denied") || lower.contains("requires elevation") {
+        return DriverError::AccessDenied;
+    }
+    if lower.contains("restart") || lower.contains("reboot") {
+        return DriverError::RebootRequired;
+    }
+    let first_line = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("no output");
+    DriverError::ScriptFailed(first_line.to_string())
+}
+
+pub struct PowerShellDriverControl {
+    friendly_name: String,
+}
+impl PowerShellDriverControl {
+    pub fn new(friendly_name: impl Into<String>) -> Self {
+        Self {
+            friendly_name: friendly_name.into(),
+        }
+    }
+    pub fn set_friendly_name(&mut self, friendly_name: impl Into<String>) {
+        self.friendly_name = friendly_name.into();
+    }
+}
+impl DisplayDriverControl for PowerShellDriverControl {
+    fn enable(&mut self) -> Result<(), DriverError> {
+        ScriptType::EnableDisplayDriver.run(&self.friendly_name)?;
+        Ok(())
+    }
+    fn disable(&mut self) -> Result<(), DriverError> {
+        ScriptType::DisableDisplayDriver.run(&self.friendly_name)?;
+        Ok(())
+    }
+    fn status(&mut self) -> Result<DriverState, DriverError> {
+        match ScriptType::QueryDisplayDriverStatus.run(&self.friendly_name) {
+            Ok(stdout) => Ok(parse_status(&stdout)),
+            Err(DriverError::DeviceNotFound(_)) => Ok(DriverState::NotPresent),
+            Err(err) => Err(err),
+        }
+    }
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<(), DriverError> {
+        ScriptType::SetResolution { width, height }.run(&self.friendly_name)?;
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DriverCall {
+    Enable,
+    Disable,
+    Status,
+    SetResolution(u32, u32),
+}
+impl DriverCall {
+    pub fn run(self, driver: &mut impl DisplayDriverControl) -> Result<DriverState, DriverError> {
+        match self {
+            DriverCall::Enable => driver.enable().map(|_| DriverState::Enabled),
+            DriverCall::Disable => driver.disable().map(|_| DriverState::Disabled),
+            DriverCall::Status => driver.status(),
+            DriverCall::SetResolution(width, height) => driver.set_resolution(width, height).map(|_| DriverState::Enabled),
+        }
+    }
+}
+
+#[cfg(test)]
+pub struct RecordingDriverControl {
+    pub calls: Vec<DriverCall>,
+    pub state: DriverState,
+    pub fail_with: Option<DriverError>,
+}
+#[cfg(test)]
+impl RecordingDriverControl {
+    pub fn new() -> Self {
+        Self {
+            calls: vec![],
+            state: DriverState::Disabled,
+            fail_with: None,
+        }
+    }
+    fn record(&mut self, call: DriverCall) -> Result<(), DriverError> {
+        self.calls.push(call);
+        match self.fail_with.clone() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+#[cfg(test)]
+impl DisplayDriverControl for RecordingDriverControl {
+    fn enable(&mut self) -> Result<(), DriverError> {
+        self.record(DriverCall::Enable)?;
+        self.state = DriverState::Enabled;
+        Ok(())
+    }
+    fn disable(&mut self) -> Result<(), DriverError> {
+        self.record(DriverCall::Disable)?;
+        self.state = DriverState::Disabled;
+        Ok(())
+    }
+    fn status(&mut self) -> Result<DriverState, DriverError> {
+        self.record(DriverCall::Status)?;
+        Ok(self.state)
+    }
+    fn set_resolution(&mut self, width: u32, height: u32) -> Result<(), DriverError> {
+        self.record(DriverCall::SetResolution(width, height))?;
+        self.state = DriverState::Enabled;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_from_stdout() {
+        assert_eq!(parse_status("OK\r\n"), DriverState::Enabled);
+        assert_eq!(parse_status("\r\nDegraded"), DriverState::Enabled);
+        assert_eq!(parse_status("Error"), DriverState::Disabled);
+        assert_eq!(parse_status("Unknown"), DriverState::NotPresent);
+        assert_eq!(parse_status(""), DriverState::NotPresent);
+        assert_eq!(parse_status("Starting"), DriverState::Unknown);
+    }
+
+    #[test]
+    fn errors_from_stderr() {
+        let not_found = "Get-PnpDevice : No matching Win32_PnPEntity objects found by CIM query for instances of the ROOT\\cimv2\\Win32_PnPEntity class on the  CIM server: SELECT * FROM Win32_PnPEntity  WHERE ((Name LIKE 'Other Device')). Verify query parameters and retry.";
+        assert_eq!(parse_error(not_found), DriverError::DeviceNotFound(String::from("Other Device")));
+        assert_eq!(parse_error("Disable-PnpDevice : Access denied"), DriverError::AccessDenied);
+        assert_eq!(parse_error("The device requires a restart"), DriverError::RebootRequired);
+        assert_eq!(parse_error("\n  something broke\nat line 3"), DriverError::ScriptFailed(String::from("something broke")));
+    }
+
+    #[test]
+    fn calls_report_the_resulting_state() {
+        let mut driver = RecordingDriverControl::new();
+        assert_eq!(DriverCall::Enable.run(&mut driver), Ok(DriverState::Enabled));
+        assert_eq!(DriverCall::Status.run(&mut driver), Ok(DriverState::Enabled));
+        assert_eq!(DriverCall::Disable.run(&mut driver), Ok(DriverState::Disabled));
+        assert_eq!(DriverCall::SetResolution(800, 600).run(&mut driver), Ok(DriverState::Enabled));
+        assert_eq!(driver.calls, vec![DriverCall::Enable, DriverCall::Status, DriverCall::Disable, DriverCall::SetResolution(800, 600)]);
+        driver.fail_with = Some(DriverError::RebootRequired);
+        assert_eq!(DriverCall::Disable.run(&mut driver), Err(DriverError::RebootRequired));
+        assert_eq!(driver.state, DriverState::Enabled);
+    }
+}
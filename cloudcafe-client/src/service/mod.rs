@@ -1,66 +1,241 @@
 mod powershell_scripts;
+mod driver_control;
 mod mouse_window;
+mod service_secret;
+mod service_log;
+mod state;
+pub mod connection;
 
-use std::io::{Read, Write};
+pub use crate::service::driver_control::DEFAULT_FRIENDLY_NAME;
+
+use std::io::{ErrorKind, Read, Write};
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream};
 use std::ops::ControlFlow;
+use std::path::Path;
 use std::process::exit;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::thread;
 use std::time::Duration;
-use bincode::{serialize, serialize_into};
+use cloudcafe_common::auth::{generate_nonce, SharedSecret};
+use cloudcafe_common::codec::{CodecError, read_message, write_message};
+use cloudcafe_common::service::{ClientToServiceMsg, negotiate, ServiceCapabilities, ServiceHello, ServiceHelloReply, ServiceToClientMsg};
 use color_eyre::{Report, Result};
 use image::{ImageBuffer, Rgba};
-use serde::{Deserialize, Serialize};
 use sysinfo::{Pid, PidExt, ProcessExt, SystemExt};
 use windows::Win32::Foundation::HWND;
 use winit::dpi::{PhysicalPosition, PhysicalSize, Position, Size};
 use winit::event_loop;
 use winit::window::Fullscreen;
-use crate::service::powershell_scripts::ScriptType;
+use crate::service::connection::ServiceConnection;
+use crate::service::service_secret::{remove_secret_file, take_launch_secret, write_launch_secret};
+use crate::service::driver_control::PowerShellDriverControl;
+use crate::service::state::{ServiceState, SessionEndReason, SessionId};
 use crate::windows_bindings::{get_console_window, Hwnd};
 
 const PORT: u16 = 25555;
 const ADDRESS: Ipv4Addr = Ipv4Addr::LOCALHOST;
 const TIMEOUT_DUR: Duration = Duration::from_secs(10);
 const SERVICE_STARTUP_WAIT: Duration = Duration::from_secs(400);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
 const ELEVATED_SERVICE_ARG: &'static str = "elevated_service";
 const WINIT_ARG: &'static str = "winit";
+const STALE_SERVICE_MSG: &'static str = "CloudCafe could not talk to its elevated service. A service from an older or newer CloudCafe install is probably still running; close its console window (or restart Windows) and launch CloudCafe again.";
 
-fn elevated_service() -> Result<()> {
+fn service_handshake(stream: &TcpStream) -> Result<Option<String>> {
+    let ours = ServiceHello::ours();
+    stream.set_read_timeout(Some(TIMEOUT_DUR))?;
+    let hello = read_message::<ServiceHello>(stream);
+    let reply = match &hello {
+        Ok(hello) => negotiate(&ours, hello),
+        Err(err) => ServiceHelloReply::Incompatible {
+            protocol_version: ours.protocol_version,
+            min_protocol_version: ours.min_protocol_version,
+            build: ours.build.clone(),
+            reason: format!("unrecognised handshake: {err}"),
+        },
+    };
+    write_message(stream, &reply)?;
+    stream.set_read_timeout(None)?;
+    match reply {
+        ServiceHelloReply::Welcome { .. } => Ok(hello.ok().and_then(|hello| hello.driver_name)),
+        ServiceHelloReply::Incompatible { reason, .. } => Err(Report::msg(reason)),
+    }
+}
+fn client_handshake(stream: &TcpStream, driver_name: &str) -> Result<ServiceCapabilities> {
+    stream.set_read_timeout(Some(TIMEOUT_DUR))?;
+    write_message(stream, &ServiceHello { driver_name: Some(driver_name.to_string()), ..ServiceHello::ours() })?;
+    let reply: ServiceHelloReply = read_message(stream).map_err(|err| Report::msg(format!("{STALE_SERVICE_MSG}\n\n({err})")))?;
+    stream.set_read_timeout(None)?;
+    match reply {
+        ServiceHelloReply::Welcome { capabilities, .. } => Ok(ServiceCapabilities::from_bits_truncate(capabilities)),
+        ServiceHelloReply::Incompatible { protocol_version, build, reason, .. } => {
+            Err(Report::msg(format!("The running CloudCafe elevated service (version {build}, protocol {protocol_version}) is incompatible with this client (version {}): {reason}.\n\nClose the elevated service's console window (or restart Windows) and launch CloudCafe again.", env!("CARGO_PKG_VERSION"))))
+        }
+    }
+}
+fn service_authenticate(stream: &TcpStream, secret: &SharedSecret) -> Result<()> {
+    let nonce = generate_nonce();
+    write_message(stream, &ServiceToClientMsg::Challenge { nonce })?;
+    stream.set_read_timeout(Some(TIMEOUT_DUR))?;
+    let msg: ClientToServiceMsg = read_message(stream)?;
+    stream.set_read_timeout(None)?;
+    let accepted = match msg {
+        ClientToServiceMsg::Authenticate { mac } => secret.verify(&nonce, &mac),
+        _ => false,
+    };
+    write_message(stream, &ServiceToClientMsg::AuthResult { accepted })?;
+    if !accepted {
+        return Err(Report::msg("peer failed the authentication challenge"));
+    }
+    Ok(())
+}
+fn client_authenticate(stream: &TcpStream, secret: &SharedSecret) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT_DUR))?;
+    let nonce = match read_message(stream)? {
+        ServiceToClientMsg::Challenge { nonce } => nonce,
+        msg => return Err(Report::msg(format!("expected an authentication challenge, got {:?}", msg))),
+    };
+    write_message(stream, &ClientToServiceMsg::Authenticate { mac: secret.respond(&nonce) })?;
+    let result: ServiceToClientMsg = read_message(stream)?;
+    stream.set_read_timeout(None)?;
+    match result {
+        ServiceToClientMsg::AuthResult { accepted: true } => Ok(()),
+        _ => Err(Report::msg("The CloudCafe elevated service rejected this client. Close the elevated service's console window (or restart Windows) and launch CloudCafe again.")),
+    }
+}
+
+fn kill_helper_process(pid: u32) {
+    let system = sysinfo::System::new_all();
+    let process = match system.process(Pid::from_u32(pid)) {
+        Some(process) => process,
+        None => {
+            println!("helper process {pid} already exited");
+            return;
+        }
+    };
+    // the pid may have been reused since the session started, only kill our own executable
+    match std::env::current_exe() {
+        Ok(exe) if exe == process.exe() => {
+            process.kill();
+        }
+        _ => service_log::record(&format!("not killing pid {pid}, it is no longer the winit helper")),
+    }
+}
+fn is_timeout(err: &CodecError) -> bool {
+    match err {
+        CodecError::Io(err) => matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut),
+        _ => false,
+    }
+}
+struct SharedService {
+    state: Mutex<ServiceState>,
+    // driver commands run one at a time under this lock, never while the state lock is held
+    driver: Mutex<PowerShellDriverControl>,
+}
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+fn handle_message(shared: &SharedService, id: SessionId, msg: ClientToServiceMsg) -> ServiceToClientMsg {
+    if !ServiceState::uses_driver(&msg) {
+        return lock(&shared.state).finish(id, msg, None);
+    }
+    let mut driver = lock(&shared.driver);
+    let call = lock(&shared.state).plan(&msg);
+    let result = call.map(|call| call.run(&mut *driver));
+    lock(&shared.state).finish(id, msg, result)
+}
+fn close_session(shared: &SharedService, id: SessionId, reason: SessionEndReason) {
+    if let Some(window_id) = lock(&shared.state).session(id).and_then(|session| session.window_id) {
+        kill_helper_process(window_id);
+    }
+    let mut driver = lock(&shared.driver);
+    let closed = lock(&shared.state).close_session(id, reason);
+    let result = closed.disable.map(|call| call.run(&mut *driver));
+    if let Some(result) = &result {
+        lock(&shared.state).driver_changed(result);
+    }
+    drop(driver);
+    match result {
+        Some(Err(err)) => service_log::record(&format!("session {id} closed ({:?}) but disabling the driver failed: {err}", closed.reason)),
+        result => service_log::record(&format!("session {id} closed ({:?}), driver disabled: {}", closed.reason, result.is_some())),
+    }
+    if lock(&shared.state).should_exit() {
+        service_log::record("last session closed, exiting");
+        exit(0);
+    }
+}
+fn serve_session(stream: TcpStream, secret: SharedSecret, shared: Arc<SharedService>) {
+    let driver_name = match service_handshake(&stream) {
+        Ok(driver_name) => driver_name,
+        Err(err) => {
+            println!("rejected client: {err}");
+            return;
+        }
+    };
+    if let Err(err) = service_authenticate(&stream, &secret) {
+        service_log::record(&format!("rejected unauthenticated peer {:?}: {err}", stream.peer_addr()));
+        return;
+    }
+    // only an authenticated client gets to pick which display driver the service controls
+    if let Some(driver_name) = driver_name {
+        println!("controlling display driver \"{driver_name}\"");
+        lock(&shared.driver).set_friendly_name(driver_name);
+    }
+    let id = lock(&shared.state).open_session();
+    println!("session {id} opened");
+    if let Err(err) = stream.set_read_timeout(Some(HEARTBEAT_TIMEOUT)) {
+        println!("unable to set heartbeat timeout: {err}");
+    }
+    let reason = loop {
+        let msg: ClientToServiceMsg = match read_message(&stream) {
+            Ok(msg) => msg,
+            Err(err) if is_timeout(&err) => break SessionEndReason::HeartbeatTimeout,
+            Err(CodecError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => break SessionEndReason::ClientDisconnected,
+            Err(err) => break SessionEndReason::ConnectionError(err.to_string()),
+        };
+        let reply = handle_message(&shared, id, msg);
+        if let Err(err) = write_message(&stream, &reply) {
+            break SessionEndReason::ConnectionError(err.to_string());
+        }
+        if reply == ServiceToClientMsg::ShuttingDown {
+            service_log::record(&format!("session {id} requested shutdown"));
+            break SessionEndReason::ShutdownRequested;
+        }
+    };
+    close_session(&shared, id, reason);
+}
+fn elevated_service(secret: SharedSecret) -> Result<()> {
     let listener = TcpListener::bind(SocketAddr::V4(SocketAddrV4::new(ADDRESS, PORT)))?;
+    let console_hwnd = get_console_window().ok_or(Report::msg("unable to get console window"))?;
+    let shared = Arc::new(SharedService {
+        state: Mutex::new(ServiceState::new(console_hwnd.0)),
+        driver: Mutex::new(PowerShellDriverControl::new(DEFAULT_FRIENDLY_NAME)),
+    });
     for stream in listener.incoming() {
-        if let Ok(mut stream) = stream {
-            let service_message: ClientToServiceMsg = bincode::deserialize_from(&stream)?;
-            match service_message {
-                ClientToServiceMsg::ProcessId{ client_id, window_id } => {
-                    ScriptType::EnableDisplayDriver.run()?;
-                    let hwnd = get_console_window().ok_or(Report::msg("unable to get console window"))?;
-                    serialize_into(stream, &ServiceToClientMsg::DisplaySetupComplete(hwnd.0))?;
-                    let client_pid = Pid::from_u32(client_id);
-                    let window_pid = Pid::from_u32(window_id);
-                    let system = sysinfo::System::new_all();
-                    let client = system.process(client_pid).ok_or(Report::msg("client PID was incorrect"))?;
-                    let window = system.process(window_pid).ok_or(Report::msg("window PID was incorrect"))?;
-                    client.wait();
-                    window.kill();
-                    //std::process::Command::new("taskkill").arg("/PID").arg(format!("{}", window_id)).arg("/F").spawn().unwrap();
-                    ScriptType::DisableDisplayDriver.run()?;
-                    thread::sleep(Duration::from_secs(1));
-                    exit(0);
-                }
-            }
+        if let Ok(stream) = stream {
+            let secret = secret.clone();
+            let shared = shared.clone();
+            thread::spawn(move || serve_session(stream, secret, shared));
         }
     }
-    exit(0);
     Ok(())
 }
-fn connect_to_service() -> Result<Hwnd> {
-    let mut stream = match TcpStream::connect_timeout(&SocketAddr::V4(SocketAddrV4::new(ADDRESS, PORT)), TIMEOUT_DUR) {
+fn connect_to_service(driver_name: &str) -> Result<ServiceConnection> {
+    let mut secret = None;
+    let mut secret_path = None;
+    let stream = match TcpStream::connect_timeout(&SocketAddr::V4(SocketAddrV4::new(ADDRESS, PORT)), TIMEOUT_DUR) {
         Ok(stream) => stream,
         Err(_) => {
-            thread::spawn(|| {
+            // a fresh secret per launch, handed over in a file only the owner and admins can read that the service deletes on startup
+            let new_secret = SharedSecret::generate();
+            let path = write_launch_secret(&new_secret)?;
+            secret.replace(new_secret);
+            secret_path.replace(path.clone());
+            thread::spawn(move || {
                 runas::Command::new(get_first_arg())
                     .arg(ELEVATED_SERVICE_ARG)
+                    .arg(path)
                     .status().unwrap()
             });
             thread::sleep(Duration::from_secs(2));
@@ -68,14 +243,25 @@ fn connect_to_service() -> Result<Hwnd> {
         }
     };
     println!("succeeded in connect");
-    let mut mouse_process = std::process::Command::new(get_first_arg()).arg(WINIT_ARG).spawn()?;
+    let handshake = client_handshake(&stream, driver_name).and_then(|capabilities| {
+        let secret = secret.ok_or(Report::msg("An elevated CloudCafe service started by an earlier launch is still running, and only that launch can talk to it. Close its console window (or restart Windows) and launch CloudCafe again."))?;
+        client_authenticate(&stream, &secret)?;
+        Ok(capabilities)
+    });
+    if let Some(path) = secret_path {
+        remove_secret_file(&path);
+    }
+    let capabilities = handshake?;
+    println!("service capabilities: {:?}", capabilities);
+    let mouse_process = std::process::Command::new(get_first_arg()).arg(WINIT_ARG).spawn()?;
     let msg = ClientToServiceMsg::ProcessId{ client_id: std::process::id(), window_id: mouse_process.id() };
-    bincode::serialize_into(&stream, &msg)?;
-    let msg: ServiceToClientMsg = bincode::deserialize_from(&stream)?;
+    write_message(&stream, &msg)?;
+    let msg: ServiceToClientMsg = read_message(&stream)?;
     return match msg {
         ServiceToClientMsg::DisplaySetupComplete(hwnd) => {
-            Ok(HWND(hwnd))
+            Ok(ServiceConnection::new(stream, HWND(hwnd), capabilities, mouse_process))
         }
+        ServiceToClientMsg::Failed(err) => Err(Report::msg(format!("elevated service was unable to set up the virtual display: {err}"))),
         _ => { Err(Report::msg("didn't get display setup msg")) }
     }
 }
@@ -102,23 +288,14 @@ fn winit_mouse_capture() -> Result<()> {
 fn get_first_arg() -> String {
     std::env::args().collect::<Vec<_>>().first().unwrap().clone()
 }
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-enum ClientToServiceMsg {
-    ProcessId{
-        client_id: u32,
-        window_id: u32,
-    }
-}
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
-enum ServiceToClientMsg {
-    DisplaySetupComplete(isize)
-}
 
-pub fn init() -> Result<Option<Hwnd>> {
+pub fn init(driver_name: &str) -> Result<Option<ServiceConnection>> {
     if let Some(arg) = std::env::args().collect::<Vec<_>>().get(1) {
         match arg.as_str() {
             ELEVATED_SERVICE_ARG => {
-                elevated_service()?;
+                let secret_path = std::env::args().nth(2).ok_or(Report::msg("elevated service was started without a shared secret"))?;
+                let secret = take_launch_secret(Path::new(&secret_path))?;
+                elevated_service(secret)?;
                 return Ok(None)
             }
             WINIT_ARG => {
@@ -128,5 +305,5 @@ pub fn init() -> Result<Option<Hwnd>> {
             _ => (),
         }
     }
-    Ok(Some(connect_to_service()?))
+    Ok(Some(connect_to_service(driver_name)?))
 }
\ No newline at end of file
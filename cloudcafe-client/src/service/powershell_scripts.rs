@@ -1,27 +1,102 @@
-use color_eyre::Result;
-#[derive(Copy, Clone, Debug)]
+use powershell_script::PsError;
+use crate::service::driver_control::{DriverError, parse_error};
+
+// single quoted strings are never expanded, powershell also accepts the typographic quotes as delimiters
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in text.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ScriptType {
     EnableDisplayDriver,
     DisableDisplayDriver,
+    QueryDisplayDriverStatus,
+    SetResolution {
+        width: u32,
+        height: u32,
+    },
 }
 impl ScriptType {
-    fn to_script(self) -> String {
-        String::from(match self {
+    fn to_script(self, friendly_name: &str) -> String {
+        let friendly_name = quote(friendly_name);
+        match self {
             ScriptType::EnableDisplayDriver => {
-r#"$ConfirmPreference = 'None'
-Get-PnpDevice -FriendlyName "IddSampleDriver Device" | Enable-PnpDevice
-"#
+format!(r#"$ConfirmPreference = 'None'
+$ErrorActionPreference = 'Stop'
+$FriendlyName = [WildcardPattern]::Escape({friendly_name})
+Get-PnpDevice -FriendlyName $FriendlyName | Enable-PnpDevice
+"#)
             }
             ScriptType::DisableDisplayDriver => {
-r#"$ConfirmPreference = 'None'
-Get-PnpDevice -FriendlyName "IddSampleDriver Device" | Disable-PnpDevice
-"#
+format!(r#"$ConfirmPreference = 'None'
+$ErrorActionPreference = 'Stop'
+$FriendlyName = [WildcardPattern]::Escape({friendly_name})
+Get-PnpDevice -FriendlyName $FriendlyName | Disable-PnpDevice
+"#)
+            }
+            ScriptType::QueryDisplayDriverStatus => {
+format!(r#"$ErrorActionPreference = 'Stop'
+$FriendlyName = [WildcardPattern]::Escape({friendly_name})
+Get-PnpDevice -FriendlyName $FriendlyName | Select-Object -ExpandProperty Status
+"#)
             }
-        })
+            ScriptType::SetResolution { width, height } => {
+format!(r#"$ConfirmPreference = 'None'
+$ErrorActionPreference = 'Stop'
+$FriendlyName = [WildcardPattern]::Escape({friendly_name})
+Set-Content -Path "C:\IddSampleDriver\option.txt" -Value "1`n{width}, {height}, 60"
+Get-PnpDevice -FriendlyName $FriendlyName | Disable-PnpDevice
+Get-PnpDevice -FriendlyName $FriendlyName | Enable-PnpDevice
+"#)
+            }
+        }
     }
-    pub fn run(self) -> Result<()> {
+    pub fn run(self, friendly_name: &str) -> Result<String, DriverError> {
         let powershell_runner = powershell_script::PsScriptBuilder::new().hidden(false).print_commands(true).build();
-        powershell_runner.run(self.to_script().as_str())?;
-        Ok(())
+        match powershell_runner.run(self.to_script(friendly_name).as_str()) {
+            Ok(output) => Ok(output.stdout().unwrap_or_default()),
+            Err(PsError::Powershell(output)) => Err(parse_error(&output.stderr().unwrap_or_default())),
+            Err(err) => Err(DriverError::Io(err.to_string())),
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPTS: [ScriptType; 4] = [ScriptType::EnableDisplayDriver, ScriptType::DisableDisplayDriver, ScriptType::QueryDisplayDriverStatus, ScriptType::SetResolution { width: 1920, height: 1080 }];
+
+    #[test]
+    fn quote_doubles_every_quote_character() {
+        assert_eq!(quote("IddSampleDriver Device"), "'IddSampleDriver Device'");
+        assert_eq!(quote("it's"), "'it''s'");
+        assert_eq!(quote("\u{2018}a\u{2019}"), "'\u{2018}\u{2018}a\u{2019}\u{2019}'");
+        assert_eq!(quote("$(calc) \"x\" `n"), "'$(calc) \"x\" `n'");
+    }
+
+    #[test]
+    fn friendly_name_cannot_escape_the_script() {
+        let name = "x'; Remove-Item -Recurse C:; '";
+        for script in SCRIPTS {
+            let text = script.to_script(name);
+            assert!(text.contains("$FriendlyName = [WildcardPattern]::Escape('x''; Remove-Item -Recurse C:; ''')\n"), "{text}");
+            assert_eq!(text.matches(name).count(), 0, "{text}");
+            assert!(text.lines().filter(|line| line.starts_with("Get-PnpDevice")).all(|line| line.starts_with("Get-PnpDevice -FriendlyName $FriendlyName |")));
+        }
+    }
+
+    #[test]
+    fn resolution_is_written_to_the_driver_options() {
+        let text = ScriptType::SetResolution { width: 2560, height: 1440 }.to_script("IddSampleDriver Device");
+        assert!(text.contains("-Value \"1`n2560, 1440, 60\""));
+    }
+}
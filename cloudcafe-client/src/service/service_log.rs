@@ -0,0 +1,22 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use color_eyre::Result;
+use crate::service::service_secret::data_dir;
+
+const LOG_FILE: &'static str = "service.log";
+
+fn append(line: &str) -> Result<()> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE))?;
+    writeln!(file, "{} {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), line)?;
+    Ok(())
+}
+
+pub fn record(line: &str) {
+    println!("{line}");
+    if let Err(err) = append(line) {
+        println!("unable to write service log: {err}");
+    }
+}
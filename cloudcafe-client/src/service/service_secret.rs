@@ -0,0 +1,34 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use cloudcafe_common::auth::SharedSecret;
+use color_eyre::{Report, Result};
+use crate::windows_bindings::create_private_file;
+
+pub fn data_dir() -> Result<PathBuf> {
+    let local_app_data = std::env::var("LOCALAPPDATA").map_err(|_| Report::msg("LOCALAPPDATA is not set"))?;
+    Ok(PathBuf::from(local_app_data).join("CloudCafe"))
+}
+
+pub fn write_launch_secret(secret: &SharedSecret) -> Result<PathBuf> {
+    let dir = data_dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("service-{}.key", std::process::id()));
+    remove_secret_file(&path);
+    let mut file = create_private_file(&path)?;
+    file.write_all(secret.to_hex().as_bytes())?;
+    Ok(path)
+}
+
+pub fn take_launch_secret(path: &Path) -> Result<SharedSecret> {
+    let hex = fs::read_to_string(path);
+    remove_secret_file(path);
+    SharedSecret::from_hex(hex?.trim()).ok_or(Report::msg(format!("{} does not hold a valid shared secret", path.display())))
+}
+
+pub fn remove_secret_file(path: &Path) {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => println!("unable to remove {}: {err}", path.display()),
+        _ => {}
+    }
+}
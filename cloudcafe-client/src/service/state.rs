@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use cloudcafe_common::service::{ClientToServiceMsg, DriverState, ServiceToClientMsg};
+#[cfg(test)]
+use crate::service::driver_control::DisplayDriverControl;
+use crate::service::driver_control::{DriverCall, DriverError};
+
+pub type SessionId = u64;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub client_id: Option<u32>,
+    pub window_id: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionEndReason {
+    ClientDisconnected,
+    ShutdownRequested,
+    HeartbeatTimeout,
+    ConnectionError(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionClosed {
+    pub window_id: Option<u32>,
+    pub disable: Option<DriverCall>,
+    pub reason: SessionEndReason,
+}
+
+pub struct ServiceState {
+    console_hwnd: isize,
+    driver_enabled: bool,
+    sessions: HashMap<SessionId, SessionInfo>,
+    next_session: SessionId,
+    last_session_end: Option<(SessionId, SessionEndReason)>,
+}
+
+impl ServiceState {
+    pub fn new(console_hwnd: isize) -> Self {
+        Self {
+            console_hwnd,
+            driver_enabled: false,
+            sessions: HashMap::new(),
+            next_session: 0,
+            last_session_end: None,
+        }
+    }
+    pub fn driver_enabled(&self) -> bool {
+        self.driver_enabled
+    }
+    // each launch hands the service a single-use secret, so once its last session is gone no client can reach it again
+    pub fn should_exit(&self) -> bool {
+        self.sessions.is_empty()
+    }
+    pub fn last_session_end(&self) -> Option<&(SessionId, SessionEndReason)> {
+        self.last_session_end.as_ref()
+    }
+    pub fn session_count(&self) -> usize {
+        self.sessions.values().filter(|session| session.client_id.is_some()).count()
+    }
+    pub fn open_session(&mut self) -> SessionId {
+        let id = self.next_session;
+        self.next_session += 1;
+        self.sessions.insert(id, SessionInfo::default());
+        id
+    }
+    pub fn session(&self, id: SessionId) -> Option<SessionInfo> {
+        self.sessions.get(&id).copied()
+    }
+    pub fn uses_driver(msg: &ClientToServiceMsg) -> bool {
+        matches!(msg, ClientToServiceMsg::ProcessId { .. } | ClientToServiceMsg::EnableDriver | ClientToServiceMsg::DisableDriver
+            | ClientToServiceMsg::QueryDriverStatus | ClientToServiceMsg::SetResolution { .. })
+    }
+    pub fn plan(&self, msg: &ClientToServiceMsg) -> Option<DriverCall> {
+        match msg {
+            ClientToServiceMsg::ProcessId { .. } | ClientToServiceMsg::EnableDriver => (!self.driver_enabled).then_some(DriverCall::Enable),
+            ClientToServiceMsg::DisableDriver => self.driver_enabled.then_some(DriverCall::Disable),
+            ClientToServiceMsg::QueryDriverStatus => Some(DriverCall::Status),
+            ClientToServiceMsg::SetResolution { width, height } => Some(DriverCall::SetResolution(*width, *height)),
+            _ => None,
+        }
+    }
+    pub fn driver_changed(&mut self, result: &Result<DriverState, DriverError>) {
+        if let Ok(state) = result {
+            self.driver_enabled = *state == DriverState::Enabled;
+        }
+    }
+    pub fn finish(&mut self, id: SessionId, msg: ClientToServiceMsg, result: Option<Result<DriverState, DriverError>>) -> ServiceToClientMsg {
+        let state = match result {
+            Some(result) => {
+                self.driver_changed(&result);
+                match result {
+                    Ok(state) => Some(state),
+                    Err(err) => return ServiceToClientMsg::Failed(err.to_string()),
+                }
+            }
+            None => None,
+        };
+        match msg {
+            ClientToServiceMsg::ProcessId { client_id, window_id } => {
+                self.sessions.insert(id, SessionInfo {
+                    client_id: Some(client_id),
+                    window_id: Some(window_id),
+                });
+                ServiceToClientMsg::DisplaySetupComplete(self.console_hwnd)
+            }
+            ClientToServiceMsg::EnableDriver | ClientToServiceMsg::DisableDriver | ClientToServiceMsg::SetResolution { .. } => ServiceToClientMsg::Ack,
+            ClientToServiceMsg::QueryDriverStatus => ServiceToClientMsg::DriverStatus {
+                state: state.unwrap_or(DriverState::Unknown),
+                sessions: self.session_count() as u32,
+            },
+            ClientToServiceMsg::Heartbeat => ServiceToClientMsg::HeartbeatAck,
+            // the session ends after this reply; the driver is disabled and the service exits once the last session has closed
+            ClientToServiceMsg::Shutdown => ServiceToClientMsg::ShuttingDown,
+            ClientToServiceMsg::Authenticate { .. } => {
+                ServiceToClientMsg::Failed(String::from("session is already authenticated"))
+            }
+        }
+    }
+    #[cfg(test)]
+    pub fn handle(&mut self, driver: &mut impl DisplayDriverControl, id: SessionId, msg: ClientToServiceMsg) -> ServiceToClientMsg {
+        let result = self.plan(&msg).map(|call| call.run(driver));
+        self.finish(id, msg, result)
+    }
+    pub fn close_session(&mut self, id: SessionId, reason: SessionEndReason) -> SessionClosed {
+        let session = self.sessions.remove(&id).unwrap_or_default();
+        self.last_session_end.replace((id, reason.clone()));
+        let last = session.client_id.is_some() && self.session_count() == 0;
+        // the client may have died mid-command after a heartbeat timeout, so don't trust driver_enabled then
+        let disable = (last && (self.driver_enabled || reason == SessionEndReason::HeartbeatTimeout)).then_some(DriverCall::Disable);
+        SessionClosed {
+            window_id: session.window_id,
+            disable,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::driver_control::RecordingDriverControl;
+    use super::*;
+
+    const CONSOLE: isize = 5;
+
+    fn process_id(client_id: u32) -> ClientToServiceMsg {
+        ClientToServiceMsg::ProcessId { client_id, window_id: client_id + 100 }
+    }
+
+    #[test]
+    fn process_id_enables_the_driver_once() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        let b = state.open_session();
+        assert_eq!(state.handle(&mut driver, a, process_id(1)), ServiceToClientMsg::DisplaySetupComplete(CONSOLE));
+        assert_eq!(state.handle(&mut driver, b, process_id(2)), ServiceToClientMsg::DisplaySetupComplete(CONSOLE));
+        assert_eq!(driver.calls, vec![DriverCall::Enable]);
+        assert!(state.driver_enabled());
+        assert_eq!(state.session_count(), 2);
+        assert_eq!(state.session(b).unwrap().window_id, Some(102));
+    }
+
+    #[test]
+    fn plan_runs_outside_the_state() {
+        let mut state = ServiceState::new(CONSOLE);
+        let a = state.open_session();
+        let msg = process_id(1);
+        assert!(ServiceState::uses_driver(&msg));
+        assert!(!ServiceState::uses_driver(&ClientToServiceMsg::Heartbeat));
+        assert_eq!(state.plan(&msg), Some(DriverCall::Enable));
+        assert_eq!(state.session_count(), 0);
+        assert!(!state.driver_enabled());
+        assert_eq!(state.finish(a, msg, Some(Ok(DriverState::Enabled))), ServiceToClientMsg::DisplaySetupComplete(CONSOLE));
+        assert_eq!(state.plan(&ClientToServiceMsg::EnableDriver), None);
+        assert_eq!(state.plan(&ClientToServiceMsg::DisableDriver), Some(DriverCall::Disable));
+        assert_eq!(state.finish(a, ClientToServiceMsg::Heartbeat, None), ServiceToClientMsg::HeartbeatAck);
+    }
+
+    #[test]
+    fn driver_failure_is_reported_and_not_committed() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        driver.fail_with = Some(DriverError::AccessDenied);
+        let a = state.open_session();
+        assert_eq!(state.handle(&mut driver, a, process_id(1)), ServiceToClientMsg::Failed(DriverError::AccessDenied.to_string()));
+        assert!(!state.driver_enabled());
+        assert_eq!(state.session_count(), 0);
+        driver.fail_with = None;
+        assert_eq!(state.handle(&mut driver, a, process_id(1)), ServiceToClientMsg::DisplaySetupComplete(CONSOLE));
+        assert_eq!(driver.calls, vec![DriverCall::Enable, DriverCall::Enable]);
+    }
+
+    #[test]
+    fn status_and_resolution_track_the_driver() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        driver.state = DriverState::Enabled;
+        assert_eq!(state.handle(&mut driver, a, ClientToServiceMsg::QueryDriverStatus), ServiceToClientMsg::DriverStatus { state: DriverState::Enabled, sessions: 0 });
+        assert!(state.driver_enabled());
+        assert_eq!(state.handle(&mut driver, a, ClientToServiceMsg::DisableDriver), ServiceToClientMsg::Ack);
+        assert!(!state.driver_enabled());
+        assert_eq!(state.handle(&mut driver, a, ClientToServiceMsg::SetResolution { width: 1920, height: 1080 }), ServiceToClientMsg::Ack);
+        assert!(state.driver_enabled());
+        assert_eq!(driver.calls, vec![DriverCall::Status, DriverCall::Disable, DriverCall::SetResolution(1920, 1080)]);
+    }
+
+    #[test]
+    fn last_session_close_disables_the_driver() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        let b = state.open_session();
+        state.handle(&mut driver, a, process_id(1));
+        state.handle(&mut driver, b, process_id(2));
+        let closed = state.close_session(a, SessionEndReason::ClientDisconnected);
+        assert_eq!(closed, SessionClosed { window_id: Some(101), disable: None, reason: SessionEndReason::ClientDisconnected });
+        let closed = state.close_session(b, SessionEndReason::ClientDisconnected);
+        assert_eq!(closed.disable, Some(DriverCall::Disable));
+        assert_eq!(state.last_session_end(), Some(&(b, SessionEndReason::ClientDisconnected)));
+    }
+
+    #[test]
+    fn heartbeat_timeout_always_disables() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        state.handle(&mut driver, a, process_id(1));
+        state.handle(&mut driver, a, ClientToServiceMsg::DisableDriver);
+        assert!(!state.driver_enabled());
+        assert_eq!(state.close_session(a, SessionEndReason::HeartbeatTimeout).disable, Some(DriverCall::Disable));
+    }
+
+    #[test]
+    fn shutdown_waits_for_the_last_session() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        let b = state.open_session();
+        state.handle(&mut driver, a, process_id(1));
+        state.handle(&mut driver, b, process_id(2));
+        assert_eq!(state.handle(&mut driver, a, ClientToServiceMsg::Shutdown), ServiceToClientMsg::ShuttingDown);
+        assert_eq!(driver.calls, vec![DriverCall::Enable]);
+        assert_eq!(state.close_session(a, SessionEndReason::ShutdownRequested).disable, None);
+        assert!(!state.should_exit());
+        assert_eq!(state.close_session(b, SessionEndReason::ClientDisconnected).disable, Some(DriverCall::Disable));
+        assert!(state.should_exit());
+    }
+
+    #[test]
+    fn exits_when_the_last_client_leaves() {
+        let mut state = ServiceState::new(CONSOLE);
+        let mut driver = RecordingDriverControl::new();
+        let a = state.open_session();
+        assert!(!state.should_exit());
+        state.handle(&mut driver, a, process_id(1));
+        state.close_session(a, SessionEndReason::ClientDisconnected);
+        assert!(state.should_exit());
+    }
+}
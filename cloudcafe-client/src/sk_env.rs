@@ -10,16 +10,21 @@ use glam::{Mat4, Quat, Vec2, Vec3};
 use glam::EulerRot::XYZ;
 use lerp::Lerp;
 use native_dialog::MessageType;
+use stereokit::color_named;
 use stereokit::color_named::WHITE;
+use stereokit::font::Font;
 use stereokit::material::{Cull, DEFAULT_ID_MATERIAL_UNLIT, DepthTest, Material, Transparency};
+use stereokit::mesh::Mesh;
 use stereokit::model::Model;
 use stereokit::shader::Shader;
+use stereokit::text::{text_add_at, TextAlign, TextStyle};
 use stereokit::values::Color128;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::Graphics::Gdi::{HDC, ReleaseDC};
 use crate::gamma_shader::{gamma_shader, start_menu_shader};
 use crate::internal_os::internal_mouse::IMouse;
-use crate::values::{cart_2_cyl, cyl_2_cart};
+use crate::values::{cart_2_cyl, cyl_2_cart, quat_lookat};
+use crate::virtual_manager::dock::{DOCK_HEIGHT, DOCK_ITEM_HEIGHT, DOCK_ITEM_WIDTH};
 use crate::virtual_manager::VDesktop;
 use crate::virtual_manager::virtual_mouse::VMouse;
 use crate::windows_bindings::{get_cursor_pos, get_dc, get_pixel, main_monitor_dimensions, set_cursor_pos};
@@ -36,6 +41,10 @@ pub struct SkEnv {
     device: Device,
     capture: Capture,
     dc: HDC,
+    dock: Model,
+    _dock_mesh: Mesh,
+    _dock_material: Material,
+    dock_text: TextStyle,
 }
 impl SkEnv {
     pub fn new(sk: &impl StereoKitContext) -> Result<Self> {
@@ -73,6 +82,13 @@ impl SkEnv {
         material.set_texture(sk, "diffuse", &capture_texture).unwrap();
         //bridge_material.set_texture(sk, "diffuse", &capture_texture).unwrap();
 
+        let dock_material = Material::copy_from_id(sk, DEFAULT_ID_MATERIAL_UNLIT)?;
+        dock_material.set_transparency(sk, Transparency::Blend);
+        dock_material.set_depth_test(sk, DepthTest::Always);
+        dock_material.set_queue_offset(sk, 100);
+        let dock_mesh = Mesh::gen_plane(sk, [1.0, 1.0], Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 1)?;
+        let dock = Model::from_mesh(sk, &dock_mesh, &dock_material).wrap_err("dock mesh")?;
+
 
 
 
@@ -88,13 +104,33 @@ impl SkEnv {
             device,
             capture,
             dc:  get_dc(HWND(0)),
+            dock,
+            _dock_mesh: dock_mesh,
+            _dock_material: dock_material,
+            dock_text: TextStyle::new(sk, Font::default(sk), 0.02, color_named::MOCCASIN),
         })
     }
+    fn draw_dock(&self, sk: &StereoKitDraw, radius: f32, v_desktop: &VDesktop) {
+        for slot in v_desktop.dock_slots() {
+            let position = v_desktop.center + cyl_2_cart(Vec3::new(radius, slot.theta, DOCK_HEIGHT));
+            let mut orientation = quat_lookat(v_desktop.center, position);
+            orientation.x = 0.0;
+            orientation.z = 0.0;
+            let orientation = orientation.normalize();
+            let color = match slot.minimized {
+                true => Color128::new(0.15, 0.15, 0.15, 0.6),
+                false => Color128::new(0.3, 0.3, 0.3, 0.9),
+            };
+            self.dock.draw(sk, Mat4::from_scale_rotation_translation(Vec3::new(DOCK_ITEM_WIDTH, DOCK_ITEM_HEIGHT, 1.0), orientation, position).into(), color, RenderLayer::Layer1);
+            text_add_at(sk, &slot.label, Mat4::from_rotation_translation(orientation, position + orientation.mul_vec3(Vec3::new(0.0, 0.0, 0.002))), &self.dock_text, TextAlign::Center, TextAlign::Center, 0.0, 0.0, 0.0, Color128::new_rgb(1.0, 1.0, 1.0));
+        }
+    }
     pub fn draw(&self, sk: &StereoKitDraw, mut radius: f32, v_desktop: &mut VDesktop, internal_mouse: &mut IMouse) {
-        let bridge_matrix = Mat4::from_scale_rotation_translation(Vec3::new(radius, radius, radius), Quat::IDENTITY, Vec3::new(0.0, -0.9, 0.0));
+        self.draw_dock(sk, radius, v_desktop);
+        let bridge_matrix = Mat4::from_scale_rotation_translation(Vec3::new(radius, radius, radius), Quat::IDENTITY, v_desktop.center + Vec3::new(0.0, -0.9, 0.0));
         radius *= 1.3;
         let second_bridge_matrix =
-            Mat4::from_scale_rotation_translation(Vec3::new(radius, radius, radius), Quat::from_euler(XYZ, 0.0, -90.0_f32.to_radians(), 0.0), Vec3::new(0.0, -0.6, 0.0));
+            Mat4::from_scale_rotation_translation(Vec3::new(radius, radius, radius), Quat::from_euler(XYZ, 0.0, -90.0_f32.to_radians(), 0.0), v_desktop.center + Vec3::new(0.0, -0.6, 0.0));
         let ray = v_desktop.v_mouse.gen_ray(sk, v_desktop.center, &second_bridge_matrix);
         let intersect = ray.model_intersect(&self.second_lip, Cull::None);
         if v_desktop.lock_cursor {
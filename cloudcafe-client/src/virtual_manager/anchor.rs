@@ -0,0 +1,149 @@
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+const FOLLOW_SMOOTHING: f32 = 0.15;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorMode {
+    #[default]
+    World,
+    Head,
+    Body,
+}
+impl AnchorMode {
+    pub const ALL: [AnchorMode; 3] = [AnchorMode::World, AnchorMode::Body, AnchorMode::Head];
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnchorMode::World => "Pin to world",
+            AnchorMode::Head => "Follow head",
+            AnchorMode::Body => "Follow body",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub position: Vec3,
+    pub orientation: Quat,
+}
+impl Frame {
+    pub fn head(position: Vec3, orientation: Quat) -> Self {
+        Self { position, orientation }
+    }
+    pub fn body(position: Vec3, orientation: Quat) -> Self {
+        Self {
+            position,
+            orientation: yaw_only(orientation),
+        }
+    }
+    pub fn to_local(self, position: Vec3, orientation: Quat) -> (Vec3, Quat) {
+        let inverse = self.orientation.inverse();
+        (inverse.mul_vec3(position - self.position), (inverse * orientation).normalize())
+    }
+    pub fn to_world(self, position: Vec3, orientation: Quat) -> (Vec3, Quat) {
+        (self.position + self.orientation.mul_vec3(position), (self.orientation * orientation).normalize())
+    }
+}
+
+pub fn yaw_only(orientation: Quat) -> Quat {
+    let forward = orientation.mul_vec3(Vec3::NEG_Z);
+    if forward.x.abs() < f32::EPSILON && forward.z.abs() < f32::EPSILON {
+        return Quat::IDENTITY;
+    }
+    Quat::from_rotation_y((-forward.x).atan2(-forward.z))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Anchor {
+    pub mode: AnchorMode,
+    pub position: Vec3,
+    pub orientation: Quat,
+}
+impl Default for Anchor {
+    fn default() -> Self {
+        Self {
+            mode: AnchorMode::World,
+            position: Vec3::ZERO,
+            orientation: Quat::IDENTITY,
+        }
+    }
+}
+impl Anchor {
+    pub fn frame(&self, head_position: Vec3, head_orientation: Quat) -> Option<Frame> {
+        match self.mode {
+            AnchorMode::World => None,
+            AnchorMode::Head => Some(Frame::head(head_position, head_orientation)),
+            AnchorMode::Body => Some(Frame::body(head_position, head_orientation)),
+        }
+    }
+    pub fn set_mode(&mut self, mode: AnchorMode, position: Vec3, orientation: Quat, head_position: Vec3, head_orientation: Quat) {
+        self.mode = mode;
+        self.rebase(position, orientation, head_position, head_orientation);
+    }
+    pub fn rebase(&mut self, position: Vec3, orientation: Quat, head_position: Vec3, head_orientation: Quat) {
+        if let Some(frame) = self.frame(head_position, head_orientation) {
+            (self.position, self.orientation) = frame.to_local(position, orientation);
+        }
+    }
+    pub fn follow(&self, position: Vec3, orientation: Quat, head_position: Vec3, head_orientation: Quat) -> Option<(Vec3, Quat)> {
+        let (target_position, target_orientation) = self.frame(head_position, head_orientation)?.to_world(self.position, self.orientation);
+        Some((position.lerp(target_position, FOLLOW_SMOOTHING), orientation.slerp(target_orientation, FOLLOW_SMOOTHING).normalize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn tilted() -> Quat {
+        Quat::from_rotation_y(0.7) * Quat::from_rotation_x(0.4) * Quat::from_rotation_z(-0.3)
+    }
+    fn same_rotation(a: Quat, b: Quat) -> bool {
+        a.dot(b).abs() > 1.0 - EPSILON
+    }
+
+    #[test]
+    fn local_and_world_round_trip() {
+        let position = Vec3::new(0.3, 1.2, -0.9);
+        let orientation = Quat::from_rotation_y(-0.2);
+        for frame in [Frame::head(Vec3::new(0.1, 1.6, 0.2), tilted()), Frame::body(Vec3::new(0.1, 1.6, 0.2), tilted())] {
+            let (local_position, local_orientation) = frame.to_local(position, orientation);
+            let (world_position, world_orientation) = frame.to_world(local_position, local_orientation);
+            assert!((world_position - position).length() < EPSILON, "{world_position}");
+            assert!(same_rotation(world_orientation, orientation));
+        }
+    }
+
+    #[test]
+    fn body_frame_ignores_pitch_and_roll() {
+        let yaw = Quat::from_rotation_y(0.7);
+        assert!(same_rotation(yaw_only(tilted()), yaw));
+        assert!(same_rotation(Frame::body(Vec3::ZERO, tilted()).orientation, yaw));
+        assert!(same_rotation(yaw_only(Quat::from_rotation_x(FRAC_PI_4)), Quat::IDENTITY));
+        assert_eq!(yaw_only(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)), Quat::IDENTITY);
+        let level = Frame::body(Vec3::ZERO, yaw);
+        let nodding = Frame::body(Vec3::ZERO, yaw * Quat::from_rotation_x(0.5));
+        let local = Vec3::new(0.0, 0.0, -1.0);
+        assert!((level.to_world(local, Quat::IDENTITY).0 - nodding.to_world(local, Quat::IDENTITY).0).length() < EPSILON);
+    }
+
+    #[test]
+    fn anchored_windows_follow_their_frame() {
+        let mut anchor = Anchor::default();
+        assert_eq!(anchor.follow(Vec3::ZERO, Quat::IDENTITY, Vec3::ZERO, Quat::IDENTITY), None);
+        let window = Vec3::new(0.0, 0.0, -1.0);
+        anchor.set_mode(AnchorMode::Head, window, Quat::IDENTITY, Vec3::ZERO, Quat::IDENTITY);
+        let turned = Quat::from_rotation_y(0.5);
+        let mut position = window;
+        let mut orientation = Quat::IDENTITY;
+        for _ in 0..200 {
+            (position, orientation) = anchor.follow(position, orientation, Vec3::ZERO, turned).unwrap();
+        }
+        assert!((position - turned.mul_vec3(window)).length() < 1e-3, "{position}");
+        assert!(same_rotation(orientation, turned));
+    }
+}
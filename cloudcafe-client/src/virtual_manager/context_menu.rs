@@ -0,0 +1,92 @@
+use glam::{Quat, Vec3};
+use crate::virtual_manager::anchor::AnchorMode;
+
+pub const MENU_ITEM_WIDTH: f32 = 0.18;
+pub const MENU_ITEM_HEIGHT: f32 = 0.035;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MenuAction {
+    Anchor(AnchorMode),
+    Minimize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuItem {
+    pub action: MenuAction,
+    pub label: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextMenu {
+    pub window: isize,
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub items: Vec<MenuItem>,
+}
+impl ContextMenu {
+    pub fn for_window(window: isize, position: Vec3, orientation: Quat, anchor: AnchorMode) -> Self {
+        let mut items = AnchorMode::ALL.iter().map(|mode| MenuItem {
+            action: MenuAction::Anchor(*mode),
+            label: match *mode == anchor {
+                true => format!("> {}", mode.label()),
+                false => mode.label().to_string(),
+            },
+        }).collect::<Vec<_>>();
+        items.push(MenuItem {
+            action: MenuAction::Minimize,
+            label: String::from("Minimize"),
+        });
+        Self {
+            window,
+            position,
+            orientation,
+            items,
+        }
+    }
+    pub fn item_center(&self, index: usize) -> Vec3 {
+        self.position + self.orientation.mul_vec3(Vec3::new(MENU_ITEM_WIDTH / 2.0, -(index as f32 + 0.5) * MENU_ITEM_HEIGHT, 0.0))
+    }
+    pub fn item_at(&self, point: Vec3) -> Option<MenuAction> {
+        let local = self.orientation.inverse().mul_vec3(point - self.position);
+        if local.x < 0.0 || local.x > MENU_ITEM_WIDTH || local.y > 0.0 {
+            return None;
+        }
+        self.items.get((-local.y / MENU_ITEM_HEIGHT) as usize).map(|item| item.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu(orientation: Quat) -> ContextMenu {
+        ContextMenu::for_window(7, Vec3::new(0.2, 1.4, -1.0), orientation, AnchorMode::Body)
+    }
+
+    #[test]
+    fn items_list_anchors_then_minimize() {
+        let menu = menu(Quat::IDENTITY);
+        assert_eq!(menu.items.iter().map(|item| item.action).collect::<Vec<_>>(), vec![
+            MenuAction::Anchor(AnchorMode::World), MenuAction::Anchor(AnchorMode::Body), MenuAction::Anchor(AnchorMode::Head), MenuAction::Minimize,
+        ]);
+        assert_eq!(menu.items[1].label, "> Follow body");
+        assert_eq!(menu.items[0].label, "Pin to world");
+    }
+
+    #[test]
+    fn item_at_maps_points_to_rows() {
+        for orientation in [Quat::IDENTITY, Quat::from_rotation_y(1.2) * Quat::from_rotation_x(-0.3)] {
+            let menu = menu(orientation);
+            for (index, item) in menu.items.iter().enumerate() {
+                assert_eq!(menu.item_at(menu.item_center(index)), Some(item.action), "{index}");
+            }
+            let point = |x: f32, y: f32| menu.position + orientation.mul_vec3(Vec3::new(x, y, 0.0));
+            assert_eq!(menu.item_at(point(0.01, -MENU_ITEM_HEIGHT * 0.99)), Some(menu.items[0].action));
+            assert_eq!(menu.item_at(point(0.01, -MENU_ITEM_HEIGHT * 1.01)), Some(menu.items[1].action));
+            assert_eq!(menu.item_at(point(-0.01, -0.01)), None);
+            assert_eq!(menu.item_at(point(MENU_ITEM_WIDTH + 0.01, -0.01)), None);
+            assert_eq!(menu.item_at(point(0.05, 0.01)), None);
+            assert_eq!(menu.item_at(point(0.05, -MENU_ITEM_HEIGHT * menu.items.len() as f32 - 0.01)), None);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::f32::consts::PI;
+use glam::{Vec2, Vec3};
+
+const MAX_SEGMENT_ANGLE: f32 = PI / 90.0;
+const MAX_SEGMENTS: u32 = 64;
+const EPSILON: f32 = 0.0001;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bend {
+    pub width: f32,
+    pub radius: f32,
+}
+impl Bend {
+    pub const FLAT: Bend = Bend {
+        width: 1.0,
+        radius: f32::INFINITY,
+    };
+    pub fn new(width: f32, radius: f32, curvature: f32) -> Self {
+        let curvature = curvature.clamp(0.0, 1.0);
+        if curvature < EPSILON || width < EPSILON || radius < EPSILON {
+            return Self { width, ..Self::FLAT };
+        }
+        Self {
+            width,
+            radius: (radius / curvature).max(width / PI),
+        }
+    }
+    pub fn is_flat(&self) -> bool {
+        !self.radius.is_finite()
+    }
+    pub fn arc_angle(&self) -> f32 {
+        match self.is_flat() {
+            true => 0.0,
+            false => self.width / self.radius,
+        }
+    }
+    pub fn segments(&self) -> u32 {
+        ((self.arc_angle() / MAX_SEGMENT_ANGLE).ceil() as u32).clamp(1, MAX_SEGMENTS)
+    }
+    fn angle_at(&self, u: f32) -> f32 {
+        (u - 0.5) * self.arc_angle()
+    }
+    pub fn surface_point(&self, uv: Vec2) -> Vec3 {
+        let y = 0.5 - uv.y;
+        if self.is_flat() {
+            return Vec3::new(uv.x - 0.5, y, 0.0);
+        }
+        let angle = self.angle_at(uv.x);
+        Vec3::new(self.radius * angle.sin() / self.width, y, self.radius * (1.0 - angle.cos()))
+    }
+    pub fn surface_normal(&self, u: f32) -> Vec3 {
+        let angle = self.angle_at(u);
+        Vec3::new(-angle.sin(), 0.0, angle.cos())
+    }
+    pub fn surface_uv(&self, local: Vec3) -> Vec2 {
+        let v = 0.5 - local.y;
+        if self.is_flat() {
+            return Vec2::new(local.x + 0.5, v);
+        }
+        let angle = (local.x * self.width / self.radius).clamp(-1.0, 1.0).asin();
+        Vec2::new(angle / self.arc_angle() + 0.5, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UVS: [(f32, f32); 6] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0), (0.5, 0.5), (0.25, 0.8)];
+
+    fn assert_round_trip(bend: Bend) {
+        for (u, v) in UVS {
+            let uv = Vec2::new(u, v);
+            let back = bend.surface_uv(bend.surface_point(uv));
+            assert!((back - uv).length() < 1e-3, "{bend:?} {uv} -> {back}");
+        }
+    }
+
+    #[test]
+    fn flat_round_trip() {
+        let bend = Bend::new(0.8, 1.3, 0.0);
+        assert!(bend.is_flat());
+        assert_eq!(bend.surface_point(Vec2::new(0.5, 0.5)), Vec3::ZERO);
+        assert_round_trip(bend);
+    }
+
+    #[test]
+    fn curved_round_trip() {
+        let bend = Bend::new(0.8, 1.3, 1.0);
+        assert!(!bend.is_flat());
+        assert_eq!(bend.surface_point(Vec2::new(0.5, 0.5)), Vec3::ZERO);
+        assert!(bend.surface_point(Vec2::new(0.0, 0.5)).z > 0.0);
+        assert_round_trip(bend);
+        // wide windows bend at most into a half cylinder
+        let widest = Bend::new(5.0, 1.3, 1.0);
+        assert!((widest.arc_angle() - PI).abs() < EPSILON);
+        assert_round_trip(widest);
+    }
+
+    #[test]
+    fn arc_angle_and_segments() {
+        assert_eq!(Bend::FLAT.arc_angle(), 0.0);
+        assert_eq!(Bend::FLAT.segments(), 1);
+        let bend = Bend::new(0.8, 1.3, 1.0);
+        assert!((bend.arc_angle() - 0.8 / 1.3).abs() < EPSILON);
+        assert_eq!(bend.segments(), (0.8 / 1.3 / MAX_SEGMENT_ANGLE).ceil() as u32);
+        let half = Bend::new(0.8, 1.3, 0.5);
+        assert!((half.arc_angle() - bend.arc_angle() / 2.0).abs() < EPSILON);
+        assert!(half.segments() < bend.segments());
+        assert_eq!(Bend::new(5.0, 1.3, 1.0).segments(), MAX_SEGMENTS.min((PI / MAX_SEGMENT_ANGLE).ceil() as u32));
+        assert_eq!(Bend::new(0.8, 1.3, 2.0), bend);
+    }
+}
@@ -0,0 +1,57 @@
+use glam::Vec3;
+use crate::values::{cart_2_cyl, cyl_2_cart};
+
+pub const MIN_RADIUS: f32 = 0.6;
+pub const MAX_RADIUS: f32 = 3.0;
+const RADIUS_STEP: f32 = 0.1;
+
+pub fn adjusted_radius(radius: f32, steps: i32) -> f32 {
+    (radius + steps as f32 * RADIUS_STEP).clamp(MIN_RADIUS, MAX_RADIUS)
+}
+
+pub fn reproject(position: Vec3, old_center: Vec3, new_center: Vec3, radius: f32) -> Vec3 {
+    let mut cyl = cart_2_cyl(position - old_center);
+    cyl.x = radius;
+    new_center + cyl_2_cart(cyl)
+}
+
+pub fn project(position: Vec3, center: Vec3, radius: f32) -> Vec3 {
+    reproject(position, center, center, radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::virtual_manager::tiling::wrap_angle;
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    #[test]
+    fn radius_steps_and_clamps() {
+        assert!((adjusted_radius(1.3, 2) - 1.5).abs() < EPSILON);
+        assert!((adjusted_radius(1.3, -3) - 1.0).abs() < EPSILON);
+        assert_eq!(adjusted_radius(1.3, 0), 1.3);
+        assert_eq!(adjusted_radius(MIN_RADIUS, -1), MIN_RADIUS);
+        assert_eq!(adjusted_radius(2.95, 5), MAX_RADIUS);
+        assert_eq!(adjusted_radius(0.1, 0), MIN_RADIUS);
+    }
+
+    #[test]
+    fn reproject_keeps_theta_and_height() {
+        let old_center = Vec3::new(0.0, 1.6, 0.0);
+        let new_center = Vec3::new(0.5, 1.2, -0.3);
+        for (theta, height) in [(-std::f32::consts::FRAC_PI_2, 0.0), (0.4, 0.3), (3.0, -0.5), (-3.1, 0.1)] {
+            let position = old_center + cyl_2_cart(Vec3::new(1.3, theta, height));
+            for radius in [0.8, 1.3, 2.5] {
+                let moved = cart_2_cyl(reproject(position, old_center, new_center, radius) - new_center);
+                assert!((moved.x - radius).abs() < EPSILON, "{moved}");
+                assert!(wrap_angle(moved.y - theta).abs() < EPSILON, "{moved} {theta}");
+                assert!((moved.z - height).abs() < EPSILON, "{moved} {height}");
+            }
+        }
+        let off_surface = Vec3::new(0.0, 1.9, -0.4);
+        let projected = project(off_surface, old_center, 1.3);
+        let cyl = cart_2_cyl(projected - old_center);
+        assert!((cyl.x - 1.3).abs() < EPSILON && (cyl.z - 0.3).abs() < EPSILON, "{cyl}");
+    }
+}
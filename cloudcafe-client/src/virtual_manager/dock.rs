@@ -0,0 +1,181 @@
+use std::f32::consts::PI;
+use crate::virtual_manager::tiling::wrap_angle;
+
+pub const DOCK_HEIGHT: f32 = -0.6;
+pub const DOCK_ITEM_HEIGHT: f32 = 0.05;
+pub const DOCK_ITEM_WIDTH: f32 = 0.2;
+const DOCK_GAP: f32 = 0.01;
+const DOCK_THETA: f32 = -PI / 2.0;
+const MAX_LABEL: usize = 24;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DockEntry {
+    pub id: isize,
+    pub title: String,
+    pub minimized: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DockSlot {
+    pub id: isize,
+    pub theta: f32,
+    pub label: String,
+    pub minimized: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dock {
+    entries: Vec<DockEntry>,
+}
+impl Dock {
+    pub fn insert(&mut self, id: isize, title: &str) {
+        if self.entries.iter().any(|entry| entry.id == id) {
+            return;
+        }
+        self.entries.push(DockEntry {
+            id,
+            title: title.to_string(),
+            minimized: false,
+        });
+    }
+    pub fn set_title(&mut self, id: isize, title: &str) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == id) {
+            if !title.is_empty() && entry.title != title {
+                entry.title = title.to_string();
+            }
+        }
+    }
+    pub fn remove(&mut self, id: isize) {
+        self.entries.retain(|entry| entry.id != id);
+    }
+    pub fn is_minimized(&self, id: isize) -> bool {
+        self.entries.iter().any(|entry| entry.id == id && entry.minimized)
+    }
+    fn set_minimized(&mut self, id: isize, minimized: bool) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) if entry.minimized != minimized => {
+                entry.minimized = minimized;
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn minimize(&mut self, id: isize) -> bool {
+        self.set_minimized(id, true)
+    }
+    pub fn restore(&mut self, id: isize) -> bool {
+        self.set_minimized(id, false)
+    }
+    pub fn slots(&self, visible: impl Fn(isize) -> bool, radius: f32) -> Vec<DockSlot> {
+        let entries = self.entries.iter().filter(|entry| visible(entry.id)).collect::<Vec<_>>();
+        entries.iter().enumerate().map(|(index, entry)| DockSlot {
+            id: entry.id,
+            theta: slot_theta(index, entries.len(), radius),
+            label: dock_label(&entry.title),
+            minimized: entry.minimized,
+        }).collect()
+    }
+}
+
+pub fn slot_theta(index: usize, count: usize, radius: f32) -> f32 {
+    let width = DOCK_ITEM_WIDTH / radius;
+    let step = width + DOCK_GAP / radius;
+    let total = count as f32 * step - DOCK_GAP / radius;
+    wrap_angle(DOCK_THETA - total / 2.0 + width / 2.0 + index as f32 * step)
+}
+
+pub fn slot_at(slots: &[DockSlot], theta: f32, height: f32, radius: f32) -> Option<isize> {
+    if (height - DOCK_HEIGHT).abs() > DOCK_ITEM_HEIGHT / 2.0 {
+        return None;
+    }
+    let half_width = DOCK_ITEM_WIDTH / radius / 2.0;
+    slots.iter().find(|slot| wrap_angle(theta - slot.theta).abs() <= half_width).map(|slot| slot.id)
+}
+
+pub fn dock_label(title: &str) -> String {
+    let title = title.trim();
+    match title.chars().count() > MAX_LABEL {
+        true => format!("{}...", title.chars().take(MAX_LABEL - 3).collect::<String>()),
+        false => title.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f32 = 1.3;
+    const EPSILON: f32 = 0.0001;
+
+    fn dock(count: isize) -> Dock {
+        let mut dock = Dock::default();
+        for id in 1..=count {
+            dock.insert(id, &format!("window {id}"));
+        }
+        dock
+    }
+
+    #[test]
+    fn slots_are_centered_on_the_front() {
+        assert!((slot_theta(0, 1, RADIUS) - DOCK_THETA).abs() < EPSILON);
+        let slots = dock(3).slots(|_| true, RADIUS);
+        assert!((slots[1].theta - DOCK_THETA).abs() < EPSILON);
+        assert!((slots[0].theta - DOCK_THETA + (slots[2].theta - DOCK_THETA)).abs() < EPSILON);
+        let step = (DOCK_ITEM_WIDTH + DOCK_GAP) / RADIUS;
+        assert!((slots[2].theta - slots[1].theta - step).abs() < EPSILON);
+        let slots = dock(2).slots(|_| true, RADIUS);
+        assert!(((slots[0].theta + slots[1].theta) / 2.0 - DOCK_THETA).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hidden_windows_leave_no_gap() {
+        let slots = dock(3).slots(|id| id != 2, RADIUS);
+        assert_eq!(slots.iter().map(|slot| slot.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert!((slots[0].theta - slot_theta(0, 2, RADIUS)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn slot_at_hits_inside_and_misses_outside() {
+        let slots = dock(2).slots(|_| true, RADIUS);
+        let half_width = DOCK_ITEM_WIDTH / RADIUS / 2.0;
+        assert_eq!(slot_at(&slots, slots[0].theta, DOCK_HEIGHT, RADIUS), Some(1));
+        assert_eq!(slot_at(&slots, slots[1].theta + half_width - EPSILON, DOCK_HEIGHT, RADIUS), Some(2));
+        assert_eq!(slot_at(&slots, slots[1].theta + half_width + EPSILON, DOCK_HEIGHT, RADIUS), None);
+        // the gap between two slots belongs to neither
+        assert_eq!(slot_at(&slots, (slots[0].theta + slots[1].theta) / 2.0, DOCK_HEIGHT, RADIUS), None);
+        assert_eq!(slot_at(&slots, slots[0].theta, DOCK_HEIGHT + DOCK_ITEM_HEIGHT / 2.0 - EPSILON, RADIUS), Some(1));
+        assert_eq!(slot_at(&slots, slots[0].theta, DOCK_HEIGHT + DOCK_ITEM_HEIGHT / 2.0 + EPSILON, RADIUS), None);
+        assert_eq!(slot_at(&[], DOCK_THETA, DOCK_HEIGHT, RADIUS), None);
+    }
+
+    #[test]
+    fn long_titles_are_truncated() {
+        assert_eq!(dock_label("  Notepad  "), "Notepad");
+        let exact = "a".repeat(MAX_LABEL);
+        assert_eq!(dock_label(&exact), exact);
+        let label = dock_label(&"é".repeat(MAX_LABEL + 1));
+        assert_eq!(label.chars().count(), MAX_LABEL);
+        assert!(label.ends_with("..."));
+    }
+
+    #[test]
+    fn minimize_and_restore() {
+        let mut dock = dock(2);
+        assert!(!dock.is_minimized(1));
+        assert!(dock.minimize(1));
+        assert!(!dock.minimize(1));
+        assert!(dock.is_minimized(1) && !dock.is_minimized(2));
+        assert!(dock.slots(|_| true, RADIUS)[0].minimized);
+        assert!(dock.restore(1));
+        assert!(!dock.restore(1));
+        assert!(!dock.minimize(99));
+        dock.minimize(2);
+        dock.remove(2);
+        assert!(!dock.is_minimized(2));
+        dock.insert(1, "renamed");
+        dock.set_title(1, "");
+        assert_eq!(dock.slots(|_| true, RADIUS)[0].label, "window 1");
+        dock.set_title(1, "renamed");
+        assert_eq!(dock.slots(|_| true, RADIUS)[0].label, "renamed");
+    }
+}
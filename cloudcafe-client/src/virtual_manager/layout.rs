@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::PathBuf;
+use color_eyre::{Report, Result};
+use serde::{Deserialize, Serialize};
+use crate::config::config_dir;
+use crate::internal_os::WindowIdentity;
+use crate::virtual_manager::anchor::AnchorMode;
+
+pub const LAYOUT_VERSION: u32 = 1;
+const LAYOUT_FILE: &'static str = "layout.toml";
+pub const ANY_TITLE: &'static str = "*";
+pub const MAX_TITLES_PER_APP: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlacement {
+    pub position: [f32; 3],
+    pub orientation: [f32; 4],
+    pub aspect_ratio: f32,
+    pub z_depth: u32,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub anchor: AnchorMode,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub exe: String,
+    pub class: String,
+    pub title_pattern: String,
+    pub placement: SavedPlacement,
+}
+impl WindowLayout {
+    pub fn matches(&self, identity: &WindowIdentity) -> bool {
+        self.exe.eq_ignore_ascii_case(&identity.exe) && self.class == identity.class && title_matches(&self.title_pattern, &identity.title)
+    }
+}
+
+pub fn title_matches(pattern: &str, title: &str) -> bool {
+    let parts = pattern.split('*').collect::<Vec<_>>();
+    if parts.len() == 1 {
+        return pattern == title;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !title.starts_with(first) || title.len() < first.len() + last.len() || !title.ends_with(last) {
+        return false;
+    }
+    let mut rest = &title[first.len()..title.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            None => return false,
+            Some(index) => rest = &rest[index + part.len()..],
+        }
+    }
+    true
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct LayoutFile {
+    version: u32,
+    #[serde(default)]
+    windows: Vec<WindowLayout>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayoutStore {
+    windows: Vec<WindowLayout>,
+}
+impl LayoutStore {
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join(LAYOUT_FILE))
+    }
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: LayoutFile = toml::from_str(text).map_err(|err| Report::msg(format!("invalid {LAYOUT_FILE}: {err}")))?;
+        if file.version != LAYOUT_VERSION {
+            return Err(Report::msg(format!("{LAYOUT_FILE} has version {}, expected {LAYOUT_VERSION}", file.version)));
+        }
+        Ok(Self {
+            windows: file.windows,
+        })
+    }
+    pub fn to_toml(&self) -> Result<String> {
+        let file = LayoutFile {
+            version: LAYOUT_VERSION,
+            windows: self.windows.clone(),
+        };
+        toml::to_string(&file).map_err(|err| Report::msg(format!("unable to serialize {LAYOUT_FILE}: {err}")))
+    }
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Ok(path) => path,
+            Err(_) => return Self::default(),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match Self::parse(&text) {
+            Ok(store) => store,
+            Err(err) => {
+                // keep the unreadable file around instead of overwriting it on the next save
+                let backup = path.with_extension("toml.bak");
+                match fs::rename(&path, &backup) {
+                    Ok(()) => println!("ignoring saved layout: {err}, moved it to {}", backup.display()),
+                    Err(rename_err) => println!("ignoring saved layout: {err}, unable to back it up: {rename_err}"),
+                }
+                Self::default()
+            }
+        }
+    }
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+    pub fn lookup(&self, identity: &WindowIdentity) -> Option<SavedPlacement> {
+        let mut matches = self.windows.iter().filter(|layout| layout.matches(identity)).collect::<Vec<_>>();
+        matches.sort_by_key(|layout| layout.title_pattern == ANY_TITLE);
+        matches.first().map(|layout| layout.placement.clone())
+    }
+    pub fn remember(&mut self, placements: &[(WindowIdentity, SavedPlacement)]) {
+        for (identity, placement) in placements {
+            if identity.exe.is_empty() {
+                continue;
+            }
+            let same_app = placements.iter().filter(|(other, _)| other.exe == identity.exe && other.class == identity.class).count();
+            let layout = WindowLayout {
+                exe: identity.exe.clone(),
+                class: identity.class.clone(),
+                title_pattern: match same_app {
+                    1 => String::from(ANY_TITLE),
+                    _ => identity.title.clone(),
+                },
+                placement: placement.clone(),
+            };
+            let same = |existing: &WindowLayout| existing.exe.eq_ignore_ascii_case(&layout.exe) && existing.class == layout.class;
+            if let Some(index) = self.windows.iter().position(|existing| same(existing) && existing.title_pattern == layout.title_pattern) {
+                self.windows.remove(index);
+            }
+            // exact titles are remembered least recently saved first, so only the newest few per app are kept
+            let titled = self.windows.iter().filter(|existing| same(existing) && existing.title_pattern != ANY_TITLE).count();
+            if layout.title_pattern != ANY_TITLE && titled >= MAX_TITLES_PER_APP {
+                if let Some(oldest) = self.windows.iter().position(|existing| same(existing) && existing.title_pattern != ANY_TITLE) {
+                    self.windows.remove(oldest);
+                }
+            }
+            self.windows.push(layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(exe: &str, title: &str) -> WindowIdentity {
+        WindowIdentity {
+            exe: exe.to_string(),
+            class: String::from("Window"),
+            title: title.to_string(),
+        }
+    }
+    fn placement(z_depth: u32) -> SavedPlacement {
+        SavedPlacement {
+            position: [0.0, 1.5, -1.3],
+            orientation: [0.0, 0.0, 0.0, 1.0],
+            aspect_ratio: 1.6,
+            z_depth,
+            workspace: None,
+            scale: 1.0,
+            anchor: AnchorMode::World,
+        }
+    }
+    fn titles(store: &LayoutStore, exe: &str) -> Vec<String> {
+        store.windows.iter().filter(|layout| layout.exe == exe).map(|layout| layout.title_pattern.clone()).collect()
+    }
+
+    #[test]
+    fn single_window_is_remembered_for_any_title() {
+        let mut store = LayoutStore::default();
+        store.remember(&[(identity("notepad.exe", "a.txt"), placement(1))]);
+        store.remember(&[(identity("notepad.exe", "b.txt"), placement(2))]);
+        assert_eq!(titles(&store, "notepad.exe"), vec![ANY_TITLE]);
+        assert_eq!(store.lookup(&identity("NOTEPAD.EXE", "c.txt")).unwrap().z_depth, 2);
+    }
+
+    #[test]
+    fn titles_per_app_are_capped_least_recent_first() {
+        let mut store = LayoutStore::default();
+        for round in 0..3 {
+            let placements = (0..MAX_TITLES_PER_APP as u32).map(|i| (identity("code.exe", &format!("file {} - Code", round * 100 + i)), placement(i))).collect::<Vec<_>>();
+            store.remember(&placements);
+        }
+        store.remember(&[(identity("code.exe", "file 200 - Code"), placement(42)), (identity("code.exe", "new - Code"), placement(43))]);
+        let titles = titles(&store, "code.exe");
+        assert_eq!(titles.len(), MAX_TITLES_PER_APP);
+        assert!(!titles.contains(&String::from("file 201 - Code")));
+        assert_eq!(&titles[MAX_TITLES_PER_APP - 2..], ["file 200 - Code", "new - Code"]);
+        assert_eq!(store.lookup(&identity("code.exe", "file 200 - Code")).unwrap().z_depth, 42);
+    }
+
+    #[test]
+    fn cap_is_per_app() {
+        let mut store = LayoutStore::default();
+        let placements = (0..MAX_TITLES_PER_APP + 2).map(|i| (identity(if i % 2 == 0 { "a.exe" } else { "b.exe" }, &i.to_string()), placement(i as u32))).collect::<Vec<_>>();
+        store.remember(&placements);
+        store.remember(&placements);
+        assert_eq!(titles(&store, "a.exe").len() + titles(&store, "b.exe").len(), MAX_TITLES_PER_APP + 2);
+    }
+
+    #[test]
+    fn round_trip_and_version_check() {
+        let mut store = LayoutStore::default();
+        store.remember(&[(identity("a.exe", "x"), placement(3)), (identity("a.exe", "y"), placement(4))]);
+        assert_eq!(LayoutStore::parse(&store.to_toml().unwrap()).unwrap(), store);
+        let err = LayoutStore::parse("version = 2").unwrap_err().to_string();
+        assert_eq!(err, format!("{LAYOUT_FILE} has version 2, expected {LAYOUT_VERSION}"));
+    }
+
+    #[test]
+    fn title_patterns() {
+        assert!(title_matches("*", "anything"));
+        assert!(title_matches("foo * bar", "foo - x - bar"));
+        assert!(title_matches("exact", "exact") && !title_matches("exact", "exactly"));
+        assert!(!title_matches("foo*", "fo"));
+        assert!(!title_matches("ab*ba", "aba"));
+    }
+}
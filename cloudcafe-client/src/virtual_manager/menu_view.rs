@@ -0,0 +1,47 @@
+use color_eyre::eyre::Context;
+use color_eyre::Result;
+use glam::{Mat4, Vec3};
+use stereokit::color_named;
+use stereokit::font::Font;
+use stereokit::lifecycle::{StereoKitContext, StereoKitDraw};
+use stereokit::material::{DEFAULT_ID_MATERIAL_UNLIT, DepthTest, Material};
+use stereokit::mesh::Mesh;
+use stereokit::model::Model;
+use stereokit::render::RenderLayer;
+use stereokit::text::{text_add_at, TextAlign, TextStyle};
+use stereokit::values::Color128;
+use crate::virtual_manager::context_menu::{ContextMenu, MENU_ITEM_HEIGHT, MENU_ITEM_WIDTH, MenuAction};
+
+pub struct MenuView {
+    model: Model,
+    _mesh: Mesh,
+    _material: Material,
+    text_style: TextStyle,
+}
+impl MenuView {
+    pub fn new(sk: &impl StereoKitContext) -> Result<Self> {
+        let material = Material::copy_from_id(sk, DEFAULT_ID_MATERIAL_UNLIT)?;
+        material.set_depth_test(sk, DepthTest::Always);
+        material.set_queue_offset(sk, 400);
+        let mesh = Mesh::gen_plane(sk, [1.0, 1.0], Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 1)?;
+        let model = Model::from_mesh(sk, &mesh, &material).wrap_err("menu mesh")?;
+        Ok(Self {
+            model,
+            _mesh: mesh,
+            _material: material,
+            text_style: TextStyle::new(sk, Font::default(sk), 0.018, color_named::MOCCASIN),
+        })
+    }
+    pub fn draw(&self, sk: &StereoKitDraw, menu: &ContextMenu, hovered: Option<MenuAction>) {
+        let lift = menu.orientation.mul_vec3(Vec3::new(0.0, 0.0, 0.01));
+        for (index, item) in menu.items.iter().enumerate() {
+            let center = menu.item_center(index) + lift;
+            let color = match hovered == Some(item.action) {
+                true => Color128::new(0.45, 0.45, 0.45, 1.0),
+                false => Color128::new(0.2, 0.2, 0.2, 1.0),
+            };
+            self.model.draw(sk, Mat4::from_scale_rotation_translation(Vec3::new(MENU_ITEM_WIDTH, MENU_ITEM_HEIGHT * 0.95, 1.0), menu.orientation, center).into(), color, RenderLayer::Layer1);
+            text_add_at(sk, &item.label, Mat4::from_rotation_translation(menu.orientation, center + lift * 0.2), &self.text_style, TextAlign::Center, TextAlign::Center, 0.0, 0.0, 0.0, Color128::new_rgb(1.0, 1.0, 1.0));
+        }
+    }
+}
@@ -1,43 +1,94 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
-use std::thread;
-use std::thread::JoinHandle;
 use std::time::Duration;
 use dxcapture::{enumerate_displays, enumerate_windows};
 use glam::{Mat4, Quat, Vec2, Vec3};
 use stereokit::lifecycle::{StereoKitContext, StereoKitDraw};
+use stereokit::input::StereoKitInput;
 use stereokit::pose::Pose;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::Graphics::Gdi::HMONITOR;
-use crate::internal_os::FakeMonitor;
+use crate::config::UserConfig;
+use crate::internal_os::{describe_display, FakeMonitor, identify_window, WindowIdentity};
+use crate::internal_os::monitor_select::{MonitorStrategy, select_monitors};
 use crate::internal_os::internal_window::IWindow;
-use crate::virtual_manager::virtual_window::{IsWindowValid, VWindow, WindowCapture};
-use crate::windows_bindings::{class_name, get_real_window_rect, get_real_window_size, get_window_rect, Hwnd, is_window, Rect, window_enabled, window_visible};
+use crate::internal_os::window_events::WindowEvent;
+use crate::internal_os::window_hook::window_source;
+use crate::virtual_manager::virtual_window::{IsWindowValid, VWindow};
+use crate::worker::{RestartPolicy, Worker};
+use crate::windows_bindings::{class_name, get_real_window_rect, get_real_window_size, get_window_rect, Hwnd, is_window, Rect, window_enabled, window_title, window_visible};
 use color_eyre::{Report, Result};
 use color_eyre::owo_colors::OwoColorize;
 use stereokit::material::Cull;
-use crate::input::{Key, KeyboardMouseState};
+use stereokit::lines::{line_addv, LinePoint};
+use stereokit::values::Color32;
+use crate::input::{clicked, Key, KeyboardMouseState, TimedEvent};
+use crate::keybindings::Action;
 use crate::input::Key::MouseLeft;
 use crate::internal_os::internal_mouse::IMouse;
-use crate::values::{cart_2_cyl, cyl_2_cart, IVec2, quat_lookat, UVec2};
+use crate::values::{cart_2_cyl, IVec2, quat_lookat, UVec2};
 use crate::virtual_manager::desktop_capture::CaptureDesktop;
-use crate::virtual_manager::virtual_mouse::{CursorType, ResizeType, VMouse};
+use crate::virtual_manager::layout::{LayoutStore, SavedPlacement};
+use crate::virtual_manager::placement::PlacementAllocator;
+use crate::virtual_manager::tiling::{Footprint, TilingEngine, TilingStrategy};
+use crate::virtual_manager::workspace::{WorkspaceId, Workspaces};
+use crate::virtual_manager::snapping::{snap, SnapSettings};
+use crate::virtual_manager::anchor::{Anchor, AnchorMode, Frame};
+use crate::virtual_manager::context_menu::{ContextMenu, MenuAction};
+use crate::virtual_manager::menu_view::MenuView;
+use crate::virtual_manager::rules::{RuleSet, WindowPolicy};
+use crate::virtual_manager::dock::{Dock, DockSlot, slot_at};
+use crate::virtual_manager::cylinder::{adjusted_radius, project, reproject};
+use crate::virtual_manager::resize::{METERS_PER_PIXEL, ResizeDrag, ResizeEdges, ResizeLimits, zoomed};
+use crate::virtual_manager::virtual_mouse::{CursorType, FLOOR_HEIGHT, VMouse};
 
 pub mod virtual_mouse;
 pub mod virtual_window;
 pub mod desktop_capture;
+pub mod placement;
+pub mod layout;
+pub mod tiling;
+pub mod workspace;
+pub mod snapping;
+pub mod resize;
+pub mod curvature;
+pub mod cylinder;
+pub mod dock;
+pub mod anchor;
+pub mod context_menu;
+pub mod menu_view;
+pub mod rules;
+
+const WINDOW_EVENTS_RESTART: RestartPolicy = RestartPolicy::OnPanic { max_restarts: 3, backoff: Duration::from_secs(1) };
 
 pub const INVALID_WINDOW_TITLES: [&'static str; 1] = ["Cloudcafe XR Desktop" /*"SteamVR",*/ /*"OBS",*/ /*"Mixed Reality Portal"*/];
 
+pub struct VMonitor {
+    pub fake_monitor: FakeMonitor,
+    pub capture_desktop: CaptureDesktop,
+}
+
 pub struct VDesktop {
-    capture_desktop: CaptureDesktop,
+    monitors: Vec<VMonitor>,
+    placement: PlacementAllocator,
+    layout: LayoutStore,
+    rules: RuleSet,
+    identities: HashMap<isize, WindowIdentity>,
+    tiling: TilingStrategy,
+    curvature: f32,
+    workspaces: Workspaces,
+    dock: Dock,
     windows: HashMap<isize, VWindow>,
-    current_list_windows_thread: JoinHandle<()>,
-    current_list_windows: Arc<Mutex<Vec<Hwnd>>>,
-    fake_monitor: FakeMonitor,
+    _window_events_worker: Worker,
+    window_events: Arc<Mutex<Vec<WindowEvent>>>,
     pub(crate) v_mouse: VMouse,
     grabbed_window: Option<(isize, Vec3)>,
-    resize_window: Option<(isize, ResizeType, Vec3)>,
+    snap_preview: Option<(isize, Pose)>,
+    resize_window: Option<ResizeDrag>,
+    context_menu: Option<ContextMenu>,
+    menu_view: MenuView,
+    head: Frame,
     pub(crate) captured_window: Option<isize>,
     skip_windows: Vec<isize>,
     console_hwnd: Hwnd,
@@ -54,24 +105,104 @@ fn is_invalid_window(window_title: &str) -> bool {
     }
     return false;
 }
+fn saved_pose(saved: Option<&SavedPlacement>, head: Frame, center: Vec3, radius: f32) -> Pose {
+    let saved = match saved {
+        None => return Pose::new(center + Vec3::new(0.0, 0.0, -radius), Quat::IDENTITY),
+        Some(saved) => saved,
+    };
+    let orientation = Quat::from_array(saved.orientation).normalize();
+    // head and body anchored windows are saved relative to their anchor, not on the cylinder
+    match saved_anchor(Some(saved)).frame(head.position, head.orientation) {
+        Some(frame) => {
+            let (position, orientation) = frame.to_world(Vec3::from_array(saved.position), orientation);
+            Pose::new(position, orientation)
+        }
+        None => Pose::new(project(center + Vec3::from_array(saved.position), center, radius), orientation),
+    }
+}
+fn saved_anchor(saved: Option<&SavedPlacement>) -> Anchor {
+    match saved {
+        Some(saved) if saved.anchor != AnchorMode::World => Anchor {
+            mode: saved.anchor,
+            position: Vec3::from_array(saved.position),
+            orientation: Quat::from_array(saved.orientation).normalize(),
+        },
+        _ => Anchor::default(),
+    }
+}
+fn restore_window_state(v_window: &mut VWindow, saved: Option<&SavedPlacement>, policy: &WindowPolicy) {
+    v_window.internal_window.set_aspect_ratio(policy.aspect_ratio.or(saved.map(|saved| saved.aspect_ratio)).unwrap_or(1.7));
+    v_window.scale = saved.map_or(1.0, |saved| saved.scale);
+    v_window.anchor = saved_anchor(saved);
+    v_window.capture_enabled = policy.capture;
+    v_window.always_on_top = policy.always_on_top;
+}
+fn rule_pose(position: [f32; 3], center: Vec3, radius: f32) -> Pose {
+    upright_pose(center, project(center + Vec3::from_array(position), center, radius))
+}
+fn window_footprints<'a>(windows: impl Iterator<Item = &'a VWindow>, center: Vec3, radius: f32) -> Vec<Footprint> {
+    windows.filter_map(|window| {
+        let size = window.world_size()?;
+        Some(Footprint::from_position(Vec3::from(window.pose.position) - center, size.x, size.y, radius))
+    }).collect()
+}
+fn upright_pose(center: Vec3, position: Vec3) -> Pose {
+    let mut orientation = quat_lookat(center, position);
+    orientation.x = 0.0;
+    orientation.z = 0.0;
+    Pose::new(position, orientation.normalize())
+}
+fn draw_outline(sk: &StereoKitDraw, pose: Pose, width: f32, height: f32) {
+    let matrix = Mat4::from_rotation_translation(Quat::from(pose.orientation), Vec3::from(pose.position));
+    let corners = [(-0.5, -0.5), (-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)].map(|(x, y)| matrix.transform_point3(Vec3::new(x * width, y * height, 0.0)));
+    for i in 0..corners.len() {
+        line_addv(sk, &LinePoint {
+            point: corners[i].into(),
+            thickness: 0.004,
+            color: Color32::new(255, 255, 255, 200),
+        }, &LinePoint {
+            point: corners[(i + 1) % corners.len()].into(),
+            thickness: 0.004,
+            color: Color32::new(255, 255, 255, 200),
+        });
+    }
+}
+fn tiled_pose(tiling: TilingStrategy, existing: &[Footprint], gaze: Vec3, size: UVec2, center: Vec3, radius: f32) -> Pose {
+    let gaze = cart_2_cyl(gaze - center);
+    let footprint = TilingEngine::new(tiling, radius).place(gaze.y, 0.0, existing, size.x as f32 * 0.0005, size.y as f32 * 0.0005);
+    upright_pose(center, center + footprint.position(radius))
+}
 impl VDesktop {
-    pub fn new(sk: &impl StereoKitContext, console_hwnd: Hwnd, radius: f32) -> Result<Self> {
-        let mut fake_monitor = None;
-            let displays = enumerate_displays();
-            for display in displays {
-                if (display.monitor_rect.right - display.monitor_rect.left) != 4000 {
-                    continue;
-                }
-                fake_monitor.replace(FakeMonitor::new(HMONITOR(display.handle as isize),Rect {
-                    left: display.monitor_rect.left,
-                    top: display.monitor_rect.top,
-                    right: display.monitor_rect.right,
-                    bottom: display.monitor_rect.bottom,
-                })?);
-            }
-        let fake_monitor = fake_monitor.ok_or(Report::msg("unable to locate monitor"))?;
-
-        let capture_desktop = CaptureDesktop::new(sk, fake_monitor)?;
+    pub fn new(sk: &impl StereoKitContext, config: &UserConfig, console_hwnd: Hwnd, radius: f32) -> Result<Self> {
+        let displays = enumerate_displays().into_iter().map(|display| describe_display(HMONITOR(display.handle as isize), Rect {
+            left: display.monitor_rect.left,
+            top: display.monitor_rect.top,
+            right: display.monitor_rect.right,
+            bottom: display.monitor_rect.bottom,
+        })).collect::<Vec<_>>();
+        let selection = select_monitors(&displays, &MonitorStrategy::new(config.monitor.clone(), &config.driver_name));
+        for rejection in &selection.rejected {
+            println!("skipping monitor {}: {}", rejection.display.device_name, rejection.reason);
+        }
+        if selection.selected.is_empty() {
+            return Err(Report::msg(selection.describe_failure()));
+        }
+        let mut monitors = Vec::new();
+        for display in &selection.selected {
+            println!("using monitor {} ({})", display.device_name, display.adapter_name);
+            let fake_monitor = FakeMonitor::new(HMONITOR(display.handle), Rect {
+                left: display.left,
+                top: display.top,
+                right: display.left + display.width as i32,
+                bottom: display.top + display.height as i32,
+            })?;
+            let capture_desktop = CaptureDesktop::new(sk, fake_monitor)?;
+            monitors.push(VMonitor {
+                fake_monitor,
+                capture_desktop,
+            });
+        }
+        let mut placement = PlacementAllocator::new(monitors.len());
 
         let mut hwnds = Vec::new();
         for window_info in enumerate_windows() {
@@ -85,76 +216,90 @@ impl VDesktop {
         }
         let mut i_windows = Vec::new();
         for hwnd in hwnds {
-            if let Ok(i_window) = IWindow::new(hwnd, fake_monitor) {
+            if let Ok(i_window) = IWindow::new(hwnd, monitors[0].fake_monitor) {
                 if i_window.size().unwrap().x != 0 && i_window.size().unwrap().y != 0 {
                     i_windows.push(i_window);
                 }
             }
         }
-        let mut windows = HashMap::new();
+        let layout = LayoutStore::load();
+        let rules = RuleSet::load();
+        let mut i_windows = i_windows.into_iter().filter_map(|i_window| {
+            let identity = identify_window(i_window.hwnd);
+            let policy = rules.evaluate(&identity);
+            if policy.ignore {
+                println!("ignoring window by rule: {}", identity.title);
+                return None;
+            }
+            let saved = layout.lookup(&identity);
+            Some((i_window, identity, saved, policy))
+        }).collect::<Vec<_>>();
+        i_windows.sort_by_key(|(_, _, saved, _)| saved.as_ref().map(|saved| saved.z_depth));
+        let mut workspaces = Workspaces::new(&config.workspaces);
+        let mut identities = HashMap::new();
+        let mut dock = Dock::default();
+        let mut windows: HashMap<isize, VWindow> = HashMap::new();
         let mut z_depth = 0;
-        for i_window in i_windows {
-            if let Ok(mut v_window) = VWindow::new(sk, i_window.hwnd, fake_monitor, Pose::new([0.0, 0.0, -radius], Quat::IDENTITY), z_depth, capture_desktop.clone()) {
-                v_window.internal_window.set_aspect_ratio(1.7);
+        for (i_window, identity, saved, policy) in i_windows {
+            let id = i_window.hwnd.0;
+            let monitor = &monitors[placement.assign(id)];
+            if let Ok(mut v_window) = VWindow::new(sk, i_window.hwnd, monitor.fake_monitor, saved_pose(saved.as_ref(), Frame::head(Vec3::ZERO, Quat::IDENTITY), Vec3::ZERO, radius), z_depth, monitor.capture_desktop.clone()) {
+                restore_window_state(&mut v_window, saved.as_ref(), &policy);
                 v_window.internal_window.move_to_inactive();
-                windows.insert(i_window.hwnd.0, v_window);
+                let saved_workspace = saved.as_ref().and_then(|saved| saved.workspace.as_deref()).or(policy.workspace.as_deref()).and_then(|name| workspaces.find(name));
+                workspaces.assign(id, saved_workspace);
+                if let (None, Some(size)) = (saved.as_ref(), v_window.internal_window.size()) {
+                    v_window.pose = match policy.position {
+                        Some(position) => rule_pose(position, Vec3::ZERO, radius),
+                        None => {
+                            let existing = window_footprints(windows.iter().filter(|(id, _)| workspaces.is_visible(**id)).map(|(_, window)| window), Vec3::ZERO, radius);
+                            tiled_pose(config.tiling, &existing, Vec3::new(0.0, 0.0, -radius), size, Vec3::ZERO, radius)
+                        }
+                    };
+                }
+                if !workspaces.is_visible(id) || !policy.capture {
+                    v_window.suspend();
+                }
+                windows.insert(id, v_window);
+                dock.insert(id, &identity.title);
+                identities.insert(id, identity);
                 z_depth += 1;
+            } else {
+                placement.remove(id);
             }
         }
 
-        let current_list_windows = Arc::new(Mutex::new(Vec::new()));
-        let c_l_w = current_list_windows.clone();
-        let current_list_windows_thread = thread::spawn(move || {
-            let c_l_w = c_l_w;
-            loop {
-                thread::sleep(Duration::from_millis(5));
-                let mut windows = Vec::new();
-                println!("enumerating windows");
-                for window_info in enumerate_windows() {
-                    let hwnd = HWND(window_info.handle as isize);
-                    if is_invalid_window(&window_info.title) {
-                        continue;
-                    }
-                    if window_info.handle as isize == console_hwnd.0 {
-                        continue;
-                    }
-                    let rect = get_window_rect(hwnd);
-                    let w = rect.right - rect.left;
-                    let h = rect.bottom - rect.top;
-                    if w < 0 || h < 0 {
-                        continue;
-                    }
-                    if w == 0 || h == 0 {
-                        continue;
-                    }
-                    if !is_window(hwnd) {
-                        continue;
-                    }
-                    if !window_visible(hwnd) {
-                        continue;
-                    }
-                    if !window_enabled(hwnd) {
-                        continue;
-                    }
-                    windows.push(hwnd);
-                }
-                println!("finished enumerating windows");
-                let mut clw = c_l_w.lock().unwrap();
-                clw.clear();
-                for i in windows {
-                    clw.push(i);
+        let window_events = Arc::new(Mutex::new(Vec::new()));
+        let w_e = window_events.clone();
+        let window_events_worker = Worker::spawn("window events", WINDOW_EVENTS_RESTART, move |cancel| {
+            let mut source = window_source();
+            while !cancel.is_cancelled() {
+                let events = source.wait();
+                if !events.is_empty() {
+                    w_e.lock().unwrap().extend(events);
                 }
             }
         });
         Ok(Self {
-            capture_desktop,
+            monitors,
+            placement,
+            layout,
+            rules,
+            identities,
+            tiling: config.tiling,
+            curvature: config.curvature,
+            workspaces,
+            dock,
             windows,
-            current_list_windows_thread,
-            current_list_windows,
-            fake_monitor,
+            _window_events_worker: window_events_worker,
+            window_events,
             v_mouse: VMouse::new(sk, radius)?,
             grabbed_window: None,
+            snap_preview: None,
             resize_window: None,
+            context_menu: None,
+            menu_view: MenuView::new(sk)?,
+            head: Frame::head(Vec3::ZERO, Quat::IDENTITY),
             captured_window: None,
             skip_windows: vec![],
             console_hwnd,
@@ -164,12 +309,8 @@ impl VDesktop {
             lock_cursor: true,
         })
     }
-    fn get_current_list_of_windows(&self) -> Vec<HWND> {
-        let mut windows = Vec::new();
-        for window in self.current_list_windows.lock().unwrap().iter() {
-            windows.push(*window);
-        }
-        windows
+    fn take_window_events(&self) -> Vec<WindowEvent> {
+        std::mem::take(&mut *self.window_events.lock().unwrap())
     }
     pub fn highest_z_depth(&mut self) -> u32 {
         let mut highest = 0;
@@ -191,12 +332,228 @@ impl VDesktop {
         self.windows.get_mut(&id)?.z_depth = highest_z;
         Some(())
     }
+    fn activate_window(&mut self, id: isize) {
+        let activation = self.placement.activate(id);
+        if let Some(evicted) = activation.evicted {
+            if let Some(window) = self.windows.get_mut(&evicted) {
+                window.internal_window.move_to_inactive();
+            }
+        }
+        let monitor = &self.monitors[activation.monitor];
+        if let Some(window) = self.windows.get_mut(&id) {
+            if activation.previous_monitor != Some(activation.monitor) {
+                window.set_monitor(monitor.fake_monitor, monitor.capture_desktop.clone());
+                window.internal_window.set_aspect_ratio(window.internal_window.aspect_ratio().unwrap_or(1.7));
+            }
+            window.internal_window.move_to_active();
+        }
+    }
     pub fn delay_run(&mut self, sk: &StereoKitDraw) {
         self.tick_counter += 1;
         if self.tick_counter != 10 {
             return;
         }
         self.tick_counter = 0;
+        if self.grabbed_window.is_none() {
+            self.save_layout();
+        }
+    }
+    pub fn save_layout(&mut self) {
+        let placements = self.windows.iter().filter_map(|(id, window)| {
+            let identity = self.identities.get(id)?.clone();
+            let (position, orientation) = match window.anchor.mode {
+                AnchorMode::World => (Vec3::from(window.pose.position) - self.center, Quat::from(window.pose.orientation)),
+                _ => (window.anchor.position, window.anchor.orientation),
+            };
+            Some((identity, SavedPlacement {
+                position: position.to_array(),
+                orientation: orientation.to_array(),
+                aspect_ratio: window.internal_window.aspect_ratio()?,
+                z_depth: window.z_depth,
+                workspace: self.workspaces.workspace_of(*id).map(|workspace| self.workspaces.name(workspace).to_string()),
+                scale: window.scale,
+                anchor: window.anchor.mode,
+            }))
+        }).collect::<Vec<_>>();
+        let previous = self.layout.clone();
+        self.layout.remember(&placements);
+        if self.layout != previous {
+            if let Err(err) = self.layout.save() {
+                println!("unable to save window layout: {err}");
+            }
+        }
+    }
+    fn visible_footprints(&self, exclude: Option<isize>) -> Vec<Footprint> {
+        window_footprints(self.windows.iter().filter(|(id, window)| self.is_shown(**id) && exclude != Some(**id) && window.anchor.mode == AnchorMode::World).map(|(_, window)| window), self.center, self.radius)
+    }
+    fn snap_pose(&self, id: isize) -> Option<(Pose, f32, f32)> {
+        let window = self.windows.get(&id)?;
+        let size = window.world_size()?;
+        let (width, height) = (size.x, size.y);
+        let dragged = Footprint::from_position(Vec3::from(window.pose.position) - self.center, width, height, self.radius);
+        let snap = snap(dragged, &self.visible_footprints(Some(id)), &SnapSettings::default());
+        if !snap.snapped() {
+            return None;
+        }
+        Some((upright_pose(self.center, self.center + snap.footprint.position(self.radius)), width, height))
+    }
+    fn top_window(&self) -> Option<isize> {
+        let top = self.windows.iter().filter(|(id, _)| self.is_shown(**id)).max_by_key(|(_, window)| window.stacking()).map(|(id, _)| *id);
+        self.captured_window.or(top)
+    }
+    fn handle_actions(&mut self, sk: &StereoKitDraw, actions: &[Action]) {
+        for action in actions {
+            match action {
+                Action::WorkspacePrevious | Action::WorkspaceNext => {
+                    let offset = if *action == Action::WorkspaceNext { 1 } else { -1 };
+                    self.switch_workspace(self.workspaces.neighbour(self.workspaces.current(), offset));
+                }
+                Action::MoveToWorkspacePrevious | Action::MoveToWorkspaceNext => {
+                    let offset = if *action == Action::MoveToWorkspaceNext { 1 } else { -1 };
+                    let target = self.workspaces.neighbour(self.workspaces.current(), offset);
+                    if let Some(id) = self.top_window() {
+                        self.move_to_workspace(id, target);
+                    }
+                }
+                Action::RadiusIncrease => self.set_radius(adjusted_radius(self.radius, 1)),
+                Action::RadiusDecrease => self.set_radius(adjusted_radius(self.radius, -1)),
+                Action::Recenter => self.recenter(Vec3::from(sk.input_head().position)),
+                _ => {}
+            }
+        }
+    }
+    pub fn set_radius(&mut self, radius: f32) {
+        self.move_cylinder(self.center, radius);
+    }
+    pub fn recenter(&mut self, center: Vec3) {
+        self.move_cylinder(center, self.radius);
+    }
+    fn move_cylinder(&mut self, center: Vec3, radius: f32) {
+        if center == self.center && radius == self.radius {
+            return;
+        }
+        println!("moving desktop cylinder to {center} with radius {radius}");
+        for window in self.windows.values_mut().filter(|window| window.anchor.mode == AnchorMode::World) {
+            window.pose = upright_pose(center, reproject(Vec3::from(window.pose.position), self.center, center, radius));
+        }
+        self.v_mouse.pos = reproject(self.v_mouse.pos, self.center, center, radius);
+        self.snap_preview.take();
+        self.resize_window.take();
+        self.center = center;
+        self.radius = radius;
+    }
+    fn follow_anchors(&mut self) {
+        let held = [self.grabbed_window.map(|(id, _)| id), self.resize_window.map(|drag| drag.id)];
+        for (id, window) in self.windows.iter_mut() {
+            let (position, orientation) = (Vec3::from(window.pose.position), Quat::from(window.pose.orientation));
+            if held.contains(&Some(*id)) {
+                window.anchor.rebase(position, orientation, self.head.position, self.head.orientation);
+            } else if let Some((position, orientation)) = window.anchor.follow(position, orientation, self.head.position, self.head.orientation) {
+                window.pose = Pose::new(position, orientation);
+            }
+        }
+    }
+    fn rebase_anchor(&mut self, id: isize) {
+        if let Some(window) = self.windows.get_mut(&id) {
+            window.anchor.rebase(Vec3::from(window.pose.position), Quat::from(window.pose.orientation), self.head.position, self.head.orientation);
+        }
+    }
+    fn set_anchor(&mut self, id: isize, mode: AnchorMode) {
+        if let Some(window) = self.windows.get_mut(&id) {
+            println!("anchoring window {id}: {mode:?}");
+            window.anchor.set_mode(mode, Vec3::from(window.pose.position), Quat::from(window.pose.orientation), self.head.position, self.head.orientation);
+        }
+    }
+    fn menu_check(&mut self, sk: &StereoKitDraw, events: &[TimedEvent]) -> bool {
+        let menu = match self.context_menu.take() {
+            None => return false,
+            Some(menu) => menu,
+        };
+        let hovered = menu.item_at(self.v_mouse.pos);
+        self.menu_view.draw(sk, &menu, hovered);
+        if clicked(events, Key::MouseLeft) {
+            match hovered {
+                Some(MenuAction::Anchor(mode)) => self.set_anchor(menu.window, mode),
+                Some(MenuAction::Minimize) => self.minimize_window(menu.window),
+                None => {}
+            }
+            return true;
+        }
+        if !clicked(events, Key::MouseRight) {
+            self.context_menu.replace(menu);
+        }
+        true
+    }
+    fn hide_window(&mut self, id: isize) {
+        if matches!(self.context_menu, Some(ContextMenu { window, .. }) if window == id) {
+            self.context_menu.take();
+        }
+        if self.captured_window == Some(id) {
+            self.captured_window.take();
+        }
+        if matches!(self.grabbed_window, Some((grabbed, _)) if grabbed == id) {
+            self.grabbed_window.take();
+        }
+        if matches!(self.resize_window, Some(drag) if drag.id == id) {
+            self.resize_window.take();
+        }
+        self.placement.deactivate(id);
+        if let Some(window) = self.windows.get_mut(&id) {
+            window.suspend();
+            window.internal_window.move_to_inactive();
+        }
+    }
+    pub fn switch_workspace(&mut self, workspace: WorkspaceId) {
+        let previous = self.workspaces.current();
+        if !self.workspaces.switch_to(workspace) {
+            return;
+        }
+        println!("switching to workspace {}", self.workspaces.name(workspace));
+        let hidden = self.windows.keys().copied().filter(|id| self.workspaces.workspace_of(*id) == Some(previous)).collect::<Vec<_>>();
+        for id in hidden {
+            self.hide_window(id);
+        }
+    }
+    pub fn move_to_workspace(&mut self, id: isize, workspace: WorkspaceId) {
+        if !self.workspaces.move_window(id, workspace) {
+            return;
+        }
+        println!("moved window {id} to workspace {}", self.workspaces.name(workspace));
+        if !self.workspaces.is_visible(id) {
+            self.hide_window(id);
+        }
+    }
+    pub fn is_shown(&self, id: isize) -> bool {
+        self.workspaces.is_visible(id) && !self.dock.is_minimized(id)
+    }
+    pub fn minimize_window(&mut self, id: isize) {
+        if self.dock.minimize(id) {
+            println!("minimized window {id}");
+            self.hide_window(id);
+        }
+    }
+    pub fn restore_window(&mut self, id: isize) {
+        if self.dock.restore(id) {
+            println!("restored window {id}");
+        }
+        self.bring_to_top(id);
+    }
+    pub fn dock_slots(&self) -> Vec<DockSlot> {
+        self.dock.slots(|id| self.workspaces.is_visible(id), self.radius)
+    }
+    fn dock_check(&mut self, events: &[TimedEvent]) -> bool {
+        let mouse = cart_2_cyl(self.v_mouse.pos - self.center);
+        let id = match slot_at(&self.dock_slots(), mouse.y, mouse.z, self.radius) {
+            None => return false,
+            Some(id) => id,
+        };
+        if clicked(events, Key::MouseLeft) {
+            self.restore_window(id);
+        }
+        true
+    }
+    pub fn has_captured_window(&self) -> bool {
+        self.captured_window.is_some()
     }
     pub fn is_focused(&self, id: isize) -> bool {
         match self.captured_window.as_ref() {
@@ -204,53 +561,116 @@ impl VDesktop {
             Some(window_id) => *window_id == id,
         }
     }
-    pub fn draw(&mut self, sk: &StereoKitDraw, internal_mouse: &mut IMouse, keyboard_mouse: &mut KeyboardMouseState, radius: &mut f32) {
+    fn is_trackable(&self, window: Hwnd) -> bool {
+        if window.0 == self.console_hwnd.0 || !is_window(window) || !window_visible(window) || !window_enabled(window) {
+            return false;
+        }
+        if is_invalid_window(&window_title(window).unwrap_or_default()) {
+            return false;
+        }
+        let rect = get_window_rect(window);
+        rect.right - rect.left > 0 && rect.bottom - rect.top > 0
+    }
+    fn track_window(&mut self, sk: &StereoKitDraw, window: Hwnd) {
+        if self.windows.contains_key(&window.0) || self.skip_windows.contains(&window.0) || !self.is_trackable(window) {
+            return;
+        }
+        let z_depth = self.highest_z_depth() + 1;
+        let identity = identify_window(window);
+        let policy = self.rules.evaluate(&identity);
+        if policy.ignore {
+            println!("ignoring window by rule: {}", identity.title);
+            self.skip_windows.push(window.0);
+            return;
+        }
+        let saved = self.layout.lookup(&identity);
+        let monitor = &self.monitors[self.placement.assign(window.0)];
+        match VWindow::new(sk, window, monitor.fake_monitor, saved_pose(saved.as_ref(), self.head, self.center, self.radius), z_depth, monitor.capture_desktop.clone()) {
+            Ok(mut v_window) => {
+                v_window.internal_window.move_to_inactive();
+                restore_window_state(&mut v_window, saved.as_ref(), &policy);
+                let rule_workspace = policy.workspace.as_deref().and_then(|name| self.workspaces.find(name));
+                self.workspaces.assign(window.0, rule_workspace);
+                if let (None, Some(size)) = (saved.as_ref(), v_window.internal_window.size()) {
+                    v_window.pose = match policy.position {
+                        Some(position) => rule_pose(position, self.center, self.radius),
+                        None => tiled_pose(self.tiling, &self.visible_footprints(None), self.v_mouse.pos, size, self.center, self.radius),
+                    };
+                }
+                if !self.workspaces.is_visible(window.0) || !policy.capture {
+                    v_window.suspend();
+                }
+                self.windows.insert(window.0, v_window);
+                self.dock.insert(window.0, &identity.title);
+                self.identities.insert(window.0, identity);
+            }
+            Err(err) => {
+                self.placement.remove(window.0);
+                self.skip_windows.push(window.0);
+                println!("new_window_err: {err}")
+            }
+        }
+    }
+    fn remove_window(&mut self, id: isize) {
+        if let Some(captured) = self.captured_window.as_ref() {
+            if *captured == id {
+                self.captured_window.take();
+            }
+        }
+        self.placement.remove(id);
+        self.identities.remove(&id);
+        self.workspaces.remove(id);
+        self.dock.remove(id);
+        drop(self.windows.remove(&id));
+    }
+    pub fn draw(&mut self, sk: &StereoKitDraw, internal_mouse: &mut IMouse, keyboard_mouse: &mut KeyboardMouseState, events: &[TimedEvent], actions: &[Action], radius: &mut f32) {
         self.delay_run(sk);
+        self.handle_actions(sk, actions);
+        let head = sk.input_head();
+        self.head = Frame::head(Vec3::from(head.position), Quat::from(head.orientation));
+        self.follow_anchors();
         let mut focus_changed = None;
         *radius = self.radius;
-        self.v_mouse.update_pos(internal_mouse.delta_pos.x, internal_mouse.delta_pos.y);
+        self.v_mouse.update_pos(internal_mouse.delta_pos.x, internal_mouse.delta_pos.y, self.center);
         internal_mouse.lock_cursor = false;
         let mut invalid_windows = Vec::new();
-        let windows = self.get_current_list_of_windows();
-        for window in windows.iter() {
-            if self.skip_windows.contains(&window.0) {
-                continue;
-            }
-            if self.windows.contains_key(&window.0) {
-                continue;
-            }
-            let z_depth = self.highest_z_depth() + 1;
-            match VWindow::new(sk, *window, self.fake_monitor, Pose::new([0.0, 0.0, -self.radius], Quat::IDENTITY), z_depth, self.capture_desktop.clone()) {
-                Ok(mut v_window) => {
-                    v_window.internal_window.move_to_inactive();
-                    v_window.internal_window.set_aspect_ratio(1.7);
-                    self.windows.insert(window.0, v_window);
+        for event in self.take_window_events() {
+            match event {
+                WindowEvent::Created { id, .. } | WindowEvent::Shown(id) | WindowEvent::Resized { id, .. } => self.track_window(sk, HWND(id)),
+                WindowEvent::TitleChanged { id, title } => {
+                    self.dock.set_title(id, &title);
+                    self.track_window(sk, HWND(id));
                 }
-                Err(err) => {
-                    self.skip_windows.push(window.0);
-                    println!("new_window_err: {err}")
+                WindowEvent::Hidden(id) | WindowEvent::Destroyed(id) => {
+                    self.skip_windows.retain(|skip| *skip != id);
+                    if self.windows.contains_key(&id) {
+                        println!("window closed or hidden: {}", id);
+                        self.remove_window(id);
+                    }
                 }
             }
         }
         let ids = self.windows.keys().map(|a| *a).collect::<Vec<_>>();
         for id in ids {
+            if !self.is_shown(id) {
+                if !self.windows[&id].is_valid() {
+                    invalid_windows.push(id);
+                }
+                continue;
+            }
             let focused = self.is_focused(id);
             println!("begin window draw");
-            if IsWindowValid::Invalid == self.windows.get_mut(&id).unwrap().draw(sk, self.radius, focused) {
+            if IsWindowValid::Invalid == self.windows.get_mut(&id).unwrap().draw(sk, self.radius, self.curvature, focused) {
                 println!("window is invalid: {}", id);
                 invalid_windows.push(id);
             }
             println!("end window draw");
         }
         for invalid_window in invalid_windows {
-            if let Some(id) = self.captured_window.as_ref() {
-                if *id == invalid_window {
-                    self.captured_window.take();
-                }
-            }
-            drop(self.windows.remove(&invalid_window));
+            self.remove_window(invalid_window);
         }
 
+        let scroll = keyboard_mouse.take_wheel().vertical;
         if !self.lock_cursor {
             internal_mouse.lock_cursor = false;
             return;
@@ -260,22 +680,32 @@ impl VDesktop {
 
         if let Some((id, offset)) = self.grabbed_window.take() {
             if self.windows.contains_key(&id) {
-                self.v_mouse.draw(sk, Vec3::new(0.0, 0.0, 0.0));
+                self.v_mouse.draw(sk, self.center);
                 if keyboard_mouse.get_input(Key::MouseLeft).active {
-                    let mut position = cart_2_cyl(self.v_mouse.pos + offset);
-                    position.x = self.radius;
-                    let position = cyl_2_cart(position);
+                    let position = project(self.v_mouse.pos + offset, self.center, self.radius);
                     self.windows.get_mut(&id).unwrap().pose.position = position.into();
                     let face_user_quat = {
                         let mut quat = quat_lookat(self.center, position);
-                        if self.v_mouse.pos.y > -0.7 {
+                        if self.v_mouse.pos.y - self.center.y > FLOOR_HEIGHT {
                             quat.x = 0.0; quat.z = 0.0;
                         }
                         quat
                     };
                     self.windows.get_mut(&id).unwrap().pose.orientation = face_user_quat.into();
+                    self.snap_preview.take();
+                    if !keyboard_mouse.get_input(Key::Control).active && self.v_mouse.pos.y - self.center.y > FLOOR_HEIGHT {
+                        if let Some((pose, width, height)) = self.snap_pose(id) {
+                            draw_outline(sk, pose, width, height);
+                            self.snap_preview.replace((id, pose));
+                        }
+                    }
                     self.grabbed_window.replace((id, offset));
                     internal_mouse.lock_cursor = true;
+                } else if let Some((preview_id, pose)) = self.snap_preview.take() {
+                    if preview_id == id {
+                        self.windows.get_mut(&id).unwrap().pose = pose;
+                        self.rebase_anchor(id);
+                    }
                 }
             }
         } else {
@@ -298,23 +728,10 @@ impl VDesktop {
                             pos.x /= size.x as f32;
                             pos.y /= size.y as f32;
 
-                            pos.y = 1.0 - pos.y;
-
-
-                            let bounds = window.window_capture.as_ref().unwrap().get_mesh().get_bounds(sk);
-                            pos.y *= bounds.dimensions.y;
-                            pos.x *= bounds.dimensions.x;
-
-
-                            pos.x = pos.x - bounds.dimensions.x / 2.0;
-                            pos.y = pos.y - bounds.dimensions.y / 2.0;
-
-                            let pos = Vec3::new(pos.x, pos.y, 0.0);
+                            let pos = window.window_capture.as_ref().unwrap().local_at(pos);
                             let mat = Mat4::from(window.matrix().unwrap());
                             let pos = mat.transform_point3(pos);
-                            let mut cyl_mouse_pos = cart_2_cyl(Vec3::new(pos.x, pos.y, pos.z));
-                            cyl_mouse_pos.x = self.radius;
-                            self.v_mouse.pos = cyl_2_cart(cyl_mouse_pos);
+                            self.v_mouse.pos = project(pos, self.center, self.radius);
                             focus_changed = Some(id);
                         } else {
                             self.captured_window.replace(id);
@@ -323,9 +740,11 @@ impl VDesktop {
                 }
             } else {
                 internal_mouse.lock_cursor = true;
-                self.resize_or_capture_check(sk, keyboard_mouse, internal_mouse, &mut focus_changed);
-                self.try_grab_window(sk, keyboard_mouse);
-                self.v_mouse.draw(sk, Vec3::new(0.0, 0.0, 0.0));
+                if !self.menu_check(sk, events) && !self.dock_check(events) {
+                    self.resize_or_capture_check(sk, keyboard_mouse, events, internal_mouse, scroll, &mut focus_changed);
+                    self.try_grab_window(sk, keyboard_mouse, events);
+                }
+                self.v_mouse.draw(sk, self.center);
             }
         }
         if let Some(changed_id) = focus_changed {
@@ -335,8 +754,7 @@ impl VDesktop {
                         let size = window.internal_window.size().unwrap();
                         //let real_size = get_real_window_size(window.internal_window.hwnd);
                         if let Some(capture) = window.window_capture.as_mut() {
-                            capture.mesh = WindowCapture::gen_mesh(sk, 0.0, 0.0, (size.x as f32 - 17.0) / size.x as f32, size.y as f32 / (size.y as f32 + 10.0)).unwrap();
-                            capture.model.set_mesh(sk, 0, &capture.mesh);
+                            capture.set_crop(sk, (0.0, 0.0, (size.x as f32 - 17.0) / size.x as f32, size.y as f32 / (size.y as f32 + 10.0))).unwrap();
                         }
                     }
                 }
@@ -348,23 +766,40 @@ impl VDesktop {
                         let pos = IVec2::from([real_rect.left, real_rect.top]);
                         //let size = window.internal_window.size().unwrap();
                         //let pos = window.internal_window.pos();
-                        let monitor_pos = self.fake_monitor.pos;
-                        let monitor_size = self.fake_monitor.size;
+                        let monitor_pos = window.internal_window.monitor().pos;
+                        let monitor_size = window.internal_window.monitor().size;
                         let crop = calculate_crop_values( monitor_pos.x, monitor_pos.y, monitor_size.x, monitor_size.y, pos.x, pos.y, size.x, size.y);
                         if let Some(capture) = window.window_capture.as_mut() {
-                            capture.mesh = WindowCapture::gen_mesh(sk, crop.0, crop.1, crop.2, crop.3).unwrap();
-                            capture.model.set_mesh(sk, 0, &capture.mesh);
+                            capture.set_crop(sk, crop).unwrap();
                         }
                     }
                 }
             }
         }
     }
-    fn try_grab_window(&mut self, sk: &StereoKitDraw, keyboard_mouse: &mut KeyboardMouseState) {
+    fn try_grab_window(&mut self, sk: &StereoKitDraw, keyboard_mouse: &mut KeyboardMouseState, events: &[TimedEvent]) {
         let mut win_bring_top = None;
+        let mut minimize = None;
+        let mut menu = None;
         for (id, window) in &self.windows {
-            let mouse_ray = self.v_mouse.gen_ray(sk, Vec3::new(0.0, 0.0, 0.0), &window.grab_bar_matrix());
+            if !self.is_shown(*id) {
+                continue;
+            }
+            let button_ray = self.v_mouse.gen_ray(sk, self.center, &window.minimize_matrix());
+            if button_ray.model_intersect(&window.grab_bar.model, Cull::None).is_some() {
+                if clicked(events, Key::MouseLeft) {
+                    minimize.replace(*id);
+                    break;
+                }
+                continue;
+            }
+            let mouse_ray = self.v_mouse.gen_ray(sk, self.center, &window.grab_bar_matrix());
             if mouse_ray.model_intersect(&window.grab_bar.model, Cull::None).is_some() {
+                if clicked(events, Key::MouseRight) {
+                    let orientation = upright_pose(self.center, self.v_mouse.pos).orientation;
+                    menu.replace(ContextMenu::for_window(*id, self.v_mouse.pos, Quat::from(orientation), window.anchor.mode));
+                    break;
+                }
                 if keyboard_mouse.get_input(Key::MouseLeft).active {
                     let offset = Vec3::from(window.pose.position) - self.v_mouse.pos;
                     self.grabbed_window.replace((*id, offset));
@@ -373,144 +808,96 @@ impl VDesktop {
                 }
             }
         }
+        if let Some(id) = minimize {
+            self.minimize_window(id);
+        }
+        if let Some(menu) = menu {
+            self.context_menu.replace(menu);
+        }
         if let Some(win_bring_top) = win_bring_top {
             self.bring_to_top(win_bring_top);
         }
     }
-    fn resize_or_capture_check(&mut self, sk: &StereoKitDraw, keyboard_mouse: &mut KeyboardMouseState, internal_mouse: &mut IMouse, focus_changed: &mut Option<isize>) {
-        if let Some((id, resize_type, offset)) = self.resize_window.take() {
-            let mut change_aspect_ratio = None;
-            if let Some(window) = self.windows.get(&id) {
-                if keyboard_mouse.get_input(Key::MouseLeft).active {
-                    let mut temp_offset = Vec3::from(window.pose.position);
-                    if let Some(window_capture) = window.window_capture.as_ref() {
-                        temp_offset.y -= (window_capture.get_mesh().get_bounds(sk).dimensions.x / 2.0);
-                        let new_offset = temp_offset - self.v_mouse.pos;
-                        match resize_type {
-                            ResizeType::Vertical => {}
-                            ResizeType::Horizontal => {
-                                // let mut offset = cart_2_cyl(offset);
-                                // offset.x = 2.0;
-                                // let mut new_offset = cart_2_cyl(new_offset);
-                                // new_offset.x = 2.0;
-                                // let dist = new_offset.z - offset.z;
-                                // if let Some(aspect_ratio) = window.internal_window.aspect_ratio() {
-                                //     change_aspect_ratio.replace(aspect_ratio + ( dist * 0.01 ));
-                                // }
-                            }
-                            ResizeType::MixedLeft => {}
-                            ResizeType::MixedRight => {}
-                        }
-                    }
-                    self.resize_window.replace((id, resize_type, offset));
+    fn resize_target(&self, drag: &ResizeDrag) -> Option<(Pose, UVec2)> {
+        let window = self.windows.get(&drag.id)?;
+        let limits = ResizeLimits::for_monitor(window.internal_window.monitor().size, window.internal_window.padding());
+        let (position, size) = drag.target(self.v_mouse.pos, &limits);
+        Some((Pose::new(position, drag.start_orientation), size))
+    }
+    fn start_resize(&mut self, id: isize, edges: ResizeEdges) {
+        let window = match self.windows.get(&id) {
+            None => return,
+            Some(window) => window,
+        };
+        if let Some(start_size) = window.internal_window.size() {
+            self.resize_window.replace(ResizeDrag {
+                id,
+                edges,
+                start_mouse: self.v_mouse.pos,
+                start_size,
+                start_position: Vec3::from(window.pose.position),
+                start_orientation: Quat::from(window.pose.orientation),
+                scale: window.scale,
+            });
+        }
+    }
+    fn finish_resize(&mut self, drag: &ResizeDrag) {
+        let (pose, size) = match self.resize_target(drag) {
+            None => return,
+            Some(target) => target,
+        };
+        let active = self.placement.is_active(drag.id);
+        if let Some(window) = self.windows.get_mut(&drag.id) {
+            window.pose = pose;
+            window.internal_window.set_size(size);
+            match active {
+                true => window.internal_window.move_to_active(),
+                false => window.internal_window.move_to_inactive(),
+            };
+        }
+        self.rebase_anchor(drag.id);
+    }
+    fn resize_or_capture_check(&mut self, sk: &StereoKitDraw, keyboard_mouse: &mut KeyboardMouseState, events: &[TimedEvent], internal_mouse: &mut IMouse, scroll: i32, focus_changed: &mut Option<isize>) {
+        if let Some(drag) = self.resize_window.take() {
+            if keyboard_mouse.get_input(Key::MouseLeft).active {
+                if let Some((pose, size)) = self.resize_target(&drag) {
+                    draw_outline(sk, pose, size.x as f32 * METERS_PER_PIXEL * drag.scale, size.y as f32 * METERS_PER_PIXEL * drag.scale);
                 }
-            }
-            if let Some(aspect_ratio) = change_aspect_ratio {
-                self.windows.get_mut(&id).unwrap().internal_window.set_aspect_ratio(aspect_ratio);
+                self.v_mouse.set_cursor_type(CursorType::Resize(drag.edges.cursor()));
+                self.resize_window.replace(drag);
+            } else {
+                self.finish_resize(&drag);
             }
         } else {
             self.v_mouse.set_cursor_type(CursorType::Point);
             let mut data_to_change = None;
             let mut resize = None;
-            for (id, window) in &self.windows {
-                let ray = self.v_mouse.gen_ray(sk, self.center, &Mat4::from(window.matrix().unwrap()));
+            let mut zoom = None;
+            let zooming = scroll != 0 && keyboard_mouse.get_input(Key::Control).active;
+            let mut stacked = self.windows.iter().filter(|(id, _)| self.is_shown(**id)).collect::<Vec<_>>();
+            stacked.sort_by_key(|(_, window)| Reverse(window.stacking()));
+            for (id, window) in stacked {
+                let matrix = match window.matrix() {
+                    None => continue,
+                    Some(matrix) => matrix,
+                };
+                let ray = self.v_mouse.gen_ray(sk, self.center, &matrix);
                 if let Some(window_capture) = window.window_capture.as_ref() {
-                    let intersect = ray.model_intersect(window_capture.get_model(self.is_focused(*id), &self.capture_desktop), Cull::None);
+                    let intersect = ray.model_intersect(window_capture.get_model(self.is_focused(*id), &window.capture_desktop), Cull::None);
                     if let Some(ray) = intersect {
-                        let bounds = window_capture.get_mesh().get_bounds(sk);
-                        let mut pos = Vec3::from(ray.pos);
-                        pos.x += bounds.dimensions.x / 2.0;
-                        pos.y += bounds.dimensions.y / 2.0;
-                        pos.x /= bounds.dimensions.x;
-                        pos.y /= bounds.dimensions.y;
-                        pos.y = 1.0 - pos.y;
-
-                        let aspect_ratio = match window.internal_window.aspect_ratio() {
-                            None => continue,
-                            Some(aspect_ratio) => aspect_ratio,
-                        };
-
-                        let mut temp_offset = Vec3::from(window.pose.position);
-                        temp_offset.y -= (bounds.dimensions.y / 2.0);
-                        let offset = temp_offset - self.v_mouse.pos;
+                        let mut pos = window_capture.uv_at(Vec3::from(ray.pos));
 
-                        let mouse_left = keyboard_mouse.get_input(Key::MouseLeft).active && keyboard_mouse.get_input(Key::MouseLeft).just_changed;
+                        let mouse_left = clicked(events, Key::MouseLeft);
 
-                        let mouse_right = keyboard_mouse.get_input(Key::MouseRight).active && keyboard_mouse.get_input(Key::MouseRight).just_changed;
-
-                        let amount = 0.03;
-                        let amount_2 = 1.0 - amount;
-                        if pos.x <= amount && pos.y >= amount_2 {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::MixedLeft));
-                            if keyboard_mouse.get_input(Key::MouseLeft).active && keyboard_mouse.get_input(Key::MouseLeft).just_changed {
-                                //resize.replace((*id))
-                                //self.resize_window.replace((*id, ResizeType::MixedLeft, offset));
-                            }
-                            break;
-                        }
-                        if pos.x <= amount && pos.y <= amount {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::MixedLeft));
-                            if keyboard_mouse.get_input(Key::MouseLeft).active {
-                                //self.resize_window.replace((*id, ResizeType::MixedLeft, offset));
-                            }
-                            break;
-                        }
-                        if pos.x >= amount_2 && pos.y >= amount {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::MixedRight));
-                            if keyboard_mouse.get_input(Key::MouseLeft).active {
-                                //self.resize_window.replace((*id, ResizeType::MixedRight, offset));
-                            }
-                            break;
-                        }
-                        if pos.x >= amount_2 && pos.y <= amount {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::MixedRight));
-                            if keyboard_mouse.get_input(Key::MouseLeft).active {
-                                //self.resize_window.replace((*id, ResizeType::MixedRight, offset));
-                            }
+                        if zooming {
+                            zoom.replace(*id);
                             break;
                         }
 
-                        if pos.x <= amount {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::Horizontal));
-                            if mouse_left {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() + 0.1));
-                                //self.resize_window.replace((*id, ResizeType::Horizontal, offset));
-                            }
-                            if mouse_right {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() - 0.1));
-                            }
-                            break;
-                        }
-                        if pos.x >= amount_2 {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::Horizontal));
+                        if let Some(edges) = ResizeEdges::from_uv(pos.x, pos.y, 0.03) {
+                            self.v_mouse.set_cursor_type(CursorType::Resize(edges.cursor()));
                             if mouse_left {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() + 0.1));
-                                //self.resize_window.replace((*id, ResizeType::Horizontal, offset));
-                            }
-                            if mouse_right {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() - 0.1));
-                            }
-                            break;
-                        }
-                        if pos.y <= amount {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::Vertical));
-                            if mouse_left {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() - 0.1));
-                                //self.resize_window.replace((*id, ResizeType::Vertical, offset));
-                            }
-                            if mouse_right {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() + 0.1));
-                            }
-                            break;
-                        }
-                        if pos.y >= amount_2 {
-                            self.v_mouse.set_cursor_type(CursorType::Resize(ResizeType::Vertical));
-                            if mouse_left {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() - 0.1));
-                                //self.resize_window.replace((*id, ResizeType::Vertical, offset));
-                            }
-                            if mouse_right {
-                                resize.replace((*id, window.internal_window.aspect_ratio().unwrap() + 0.1));
+                                resize.replace((*id, edges));
                             }
                             break;
                         }
@@ -526,18 +913,21 @@ impl VDesktop {
                     }
                 }
             }
-            if let Some((id, aspect_ratio)) = resize {
+            if let Some(id) = zoom {
+                if let Some(window) = self.windows.get_mut(&id) {
+                    window.scale = zoomed(window.scale, scroll);
+                }
+                return;
+            }
+            if let Some((id, edges)) = resize {
                 self.bring_to_top(id);
-                self.windows.get_mut(&id).unwrap().internal_window.set_aspect_ratio(aspect_ratio);
+                self.start_resize(id, edges);
             }
             if let Some((mut pos, id)) = data_to_change {
                 focus_changed.replace(id);
                 self.bring_to_top(id);
-                for (_, win) in &mut self.windows {
-                    win.internal_window.move_to_inactive();
-                }
+                self.activate_window(id);
                 let window = self.windows.get_mut(&id).unwrap();
-                window.internal_window.move_to_active();
                 self.captured_window.replace(id);
                 pos.x += window.internal_window.pos().x;
                 pos.y += window.internal_window.pos().y;
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+pub type MonitorIndex = usize;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Activation {
+    pub monitor: MonitorIndex,
+    pub previous_monitor: Option<MonitorIndex>,
+    pub evicted: Option<isize>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct MonitorSlots {
+    active: Option<isize>,
+    last_activated: u64,
+}
+
+pub struct PlacementAllocator {
+    monitors: Vec<MonitorSlots>,
+    assigned: HashMap<isize, MonitorIndex>,
+    clock: u64,
+}
+impl PlacementAllocator {
+    pub fn new(monitor_count: usize) -> Self {
+        Self {
+            monitors: vec![MonitorSlots::default(); monitor_count.max(1)],
+            assigned: HashMap::new(),
+            clock: 0,
+        }
+    }
+    pub fn monitor_of(&self, id: isize) -> Option<MonitorIndex> {
+        self.assigned.get(&id).copied()
+    }
+    pub fn is_active(&self, id: isize) -> bool {
+        self.monitors.iter().any(|slots| slots.active == Some(id))
+    }
+    fn window_count(&self, monitor: MonitorIndex) -> usize {
+        self.assigned.values().filter(|assigned| **assigned == monitor).count()
+    }
+    pub fn assign(&mut self, id: isize) -> MonitorIndex {
+        if let Some(monitor) = self.monitor_of(id) {
+            return monitor;
+        }
+        let monitor = (0..self.monitors.len()).min_by_key(|monitor| self.window_count(*monitor)).unwrap_or(0);
+        self.assigned.insert(id, monitor);
+        monitor
+    }
+    pub fn activate(&mut self, id: isize) -> Activation {
+        self.clock += 1;
+        let previous_monitor = self.monitor_of(id);
+        if let Some(monitor) = previous_monitor {
+            if self.monitors[monitor].active == Some(id) {
+                self.monitors[monitor].last_activated = self.clock;
+                return Activation {
+                    monitor,
+                    previous_monitor,
+                    evicted: None,
+                };
+            }
+        }
+        let monitor = match previous_monitor.filter(|monitor| self.monitors[*monitor].active.is_none()) {
+            Some(monitor) => monitor,
+            None => {
+                let free = (0..self.monitors.len()).find(|monitor| self.monitors[*monitor].active.is_none());
+                free.unwrap_or_else(|| (0..self.monitors.len()).min_by_key(|monitor| self.monitors[*monitor].last_activated).unwrap_or(0))
+            }
+        };
+        let evicted = self.monitors[monitor].active.replace(id);
+        self.monitors[monitor].last_activated = self.clock;
+        self.assigned.insert(id, monitor);
+        Activation {
+            monitor,
+            previous_monitor,
+            evicted,
+        }
+    }
+    pub fn deactivate(&mut self, id: isize) {
+        for slots in &mut self.monitors {
+            if slots.active == Some(id) {
+                slots.active = None;
+            }
+        }
+    }
+    pub fn remove(&mut self, id: isize) {
+        self.deactivate(id);
+        self.assigned.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activate_fills_free_monitors_first() {
+        let mut placement = PlacementAllocator::new(2);
+        assert_eq!(placement.activate(10), Activation { monitor: 0, previous_monitor: None, evicted: None });
+        assert_eq!(placement.activate(20), Activation { monitor: 1, previous_monitor: None, evicted: None });
+        assert!(placement.is_active(10) && placement.is_active(20));
+        assert_eq!(placement.activate(10), Activation { monitor: 0, previous_monitor: Some(0), evicted: None });
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_window() {
+        let mut placement = PlacementAllocator::new(2);
+        placement.activate(10);
+        placement.activate(20);
+        placement.activate(10);
+        assert_eq!(placement.activate(30), Activation { monitor: 1, previous_monitor: None, evicted: Some(20) });
+        assert!(!placement.is_active(20));
+        assert_eq!(placement.monitor_of(20), Some(1));
+        assert_eq!(placement.activate(20), Activation { monitor: 0, previous_monitor: Some(1), evicted: Some(10) });
+        assert_eq!(placement.monitor_of(10), Some(0));
+    }
+
+    #[test]
+    fn deactivate_and_remove_free_the_slot() {
+        let mut placement = PlacementAllocator::new(1);
+        placement.activate(10);
+        placement.deactivate(10);
+        assert!(!placement.is_active(10));
+        assert_eq!(placement.monitor_of(10), Some(0));
+        assert_eq!(placement.activate(20).evicted, None);
+        placement.remove(20);
+        assert_eq!(placement.monitor_of(20), None);
+        assert_eq!(placement.activate(10), Activation { monitor: 0, previous_monitor: Some(0), evicted: None });
+    }
+
+    #[test]
+    fn assign_spreads_windows_over_monitors() {
+        let mut placement = PlacementAllocator::new(2);
+        assert_eq!([placement.assign(1), placement.assign(2), placement.assign(3), placement.assign(1)], [0, 1, 0, 0]);
+        assert!(!placement.is_active(1));
+        assert_eq!(PlacementAllocator::new(0).assign(1), 0);
+    }
+}
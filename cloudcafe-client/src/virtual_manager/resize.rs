@@ -0,0 +1,197 @@
+use glam::{Quat, Vec2, Vec3};
+use crate::values::UVec2;
+use crate::virtual_manager::virtual_mouse::ResizeType;
+
+pub const METERS_PER_PIXEL: f32 = 0.0005;
+pub const MIN_SCALE: f32 = 0.25;
+pub const MAX_SCALE: f32 = 4.0;
+const ZOOM_STEP: f32 = 1.1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResizeLimits {
+    pub min: UVec2,
+    pub max: UVec2,
+}
+impl ResizeLimits {
+    pub fn for_monitor(monitor_size: UVec2, padding: u32) -> Self {
+        let max = UVec2::from([
+            (monitor_size.x.saturating_sub(padding * 2) / 2).max(1),
+            monitor_size.y.saturating_sub(padding * 2).max(1),
+        ]);
+        Self {
+            min: [320.min(max.x), 200.min(max.y)].into(),
+            max,
+        }
+    }
+    pub fn clamp(&self, size: Vec2) -> UVec2 {
+        [
+            (size.x.round().max(0.0) as u32).clamp(self.min.x, self.max.x),
+            (size.y.round().max(0.0) as u32).clamp(self.min.y, self.max.y),
+        ].into()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResizeEdges {
+    pub horizontal: i32,
+    pub vertical: i32,
+}
+impl ResizeEdges {
+    pub fn from_uv(u: f32, v: f32, border: f32) -> Option<Self> {
+        let horizontal = match u {
+            u if u <= border => -1,
+            u if u >= 1.0 - border => 1,
+            _ => 0,
+        };
+        let vertical = match v {
+            v if v <= border => -1,
+            v if v >= 1.0 - border => 1,
+            _ => 0,
+        };
+        match (horizontal, vertical) {
+            (0, 0) => None,
+            _ => Some(Self { horizontal, vertical }),
+        }
+    }
+    pub fn cursor(&self) -> ResizeType {
+        match (self.horizontal, self.vertical) {
+            (0, _) => ResizeType::Vertical,
+            (_, 0) => ResizeType::Horizontal,
+            (-1, _) => ResizeType::MixedLeft,
+            _ => ResizeType::MixedRight,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResizeDrag {
+    pub id: isize,
+    pub edges: ResizeEdges,
+    pub start_mouse: Vec3,
+    pub start_size: UVec2,
+    pub start_position: Vec3,
+    pub start_orientation: Quat,
+    pub scale: f32,
+}
+impl ResizeDrag {
+    pub fn target(&self, mouse: Vec3, limits: &ResizeLimits) -> (Vec3, UVec2) {
+        let right = self.start_orientation.mul_vec3(Vec3::X);
+        let up = self.start_orientation.mul_vec3(Vec3::Y);
+        let delta = mouse - self.start_mouse;
+        let meters_per_pixel = METERS_PER_PIXEL * self.scale;
+        let delta_pixels = Vec2::new(delta.dot(right), -delta.dot(up)) / meters_per_pixel;
+        let size = resized(self.start_size, self.edges, delta_pixels, limits);
+        let shift = center_shift(self.start_size, size, self.edges) * meters_per_pixel;
+        (self.start_position + right * shift.x - up * shift.y, size)
+    }
+}
+
+pub fn resized(start: UVec2, edges: ResizeEdges, delta_pixels: Vec2, limits: &ResizeLimits) -> UVec2 {
+    let size = Vec2::new(
+        start.x as f32 + delta_pixels.x * edges.horizontal as f32,
+        start.y as f32 + delta_pixels.y * edges.vertical as f32,
+    );
+    limits.clamp(size)
+}
+
+pub fn center_shift(start: UVec2, new: UVec2, edges: ResizeEdges) -> Vec2 {
+    Vec2::new(
+        (new.x as f32 - start.x as f32) / 2.0 * edges.horizontal as f32,
+        (new.y as f32 - start.y as f32) / 2.0 * edges.vertical as f32,
+    )
+}
+
+pub fn zoomed(scale: f32, notches: i32) -> f32 {
+    (scale * ZOOM_STEP.powi(notches)).clamp(MIN_SCALE, MAX_SCALE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: [u32; 2] = [3840, 1080];
+
+    fn limits() -> ResizeLimits {
+        ResizeLimits::for_monitor(MONITOR.into(), 8)
+    }
+    fn edges(horizontal: i32, vertical: i32) -> ResizeEdges {
+        ResizeEdges { horizontal, vertical }
+    }
+
+    #[test]
+    fn limits_come_from_the_monitor() {
+        let limits = limits();
+        assert_eq!(limits.max, UVec2::from([1912, 1064]));
+        assert_eq!(limits.min, UVec2::from([320, 200]));
+        let tiny = ResizeLimits::for_monitor([100, 100].into(), 8);
+        assert_eq!(tiny.min, tiny.max);
+    }
+
+    #[test]
+    fn clamps_to_min_and_max() {
+        let limits = limits();
+        assert_eq!(limits.clamp(Vec2::new(800.4, 600.6)), UVec2::from([800, 601]));
+        assert_eq!(limits.clamp(Vec2::new(10.0, -50.0)), limits.min);
+        assert_eq!(limits.clamp(Vec2::new(5000.0, 5000.0)), limits.max);
+        assert_eq!(resized([800, 600].into(), edges(1, 1), Vec2::new(-1000.0, -1000.0), &limits), limits.min);
+        assert_eq!(resized([800, 600].into(), edges(-1, 0), Vec2::new(-5000.0, 300.0), &limits), UVec2::from([1912, 600]));
+    }
+
+    #[test]
+    fn dragging_an_edge_keeps_the_opposite_edge_still() {
+        let limits = limits();
+        let start = UVec2::from([800, 600]);
+        // dragging the left edge left grows the window and moves its center left
+        let size = resized(start, edges(-1, 0), Vec2::new(-100.0, 0.0), &limits);
+        assert_eq!(size, UVec2::from([900, 600]));
+        assert_eq!(center_shift(start, size, edges(-1, 0)), Vec2::new(-50.0, 0.0));
+        // dragging the top edge down shrinks the window and moves its center down
+        let size = resized(start, edges(0, -1), Vec2::new(0.0, 100.0), &limits);
+        assert_eq!(size, UVec2::from([800, 500]));
+        assert_eq!(center_shift(start, size, edges(0, -1)), Vec2::new(0.0, 50.0));
+        let size = resized(start, edges(1, 1), Vec2::new(100.0, 100.0), &limits);
+        assert_eq!(center_shift(start, size, edges(1, 1)), Vec2::new(50.0, 50.0));
+    }
+
+    #[test]
+    fn drag_target_moves_the_center_in_world_space() {
+        let drag = ResizeDrag {
+            id: 1,
+            edges: edges(-1, 0),
+            start_mouse: Vec3::new(-0.2, 0.0, -1.0),
+            start_size: [800, 600].into(),
+            start_position: Vec3::new(0.0, 0.0, -1.0),
+            start_orientation: Quat::IDENTITY,
+            scale: 1.0,
+        };
+        let (position, size) = drag.target(Vec3::new(-0.25, 0.0, -1.0), &limits());
+        assert_eq!(size, UVec2::from([900, 600]));
+        assert!((position - Vec3::new(-0.025, 0.0, -1.0)).length() < 1e-4, "{position}");
+    }
+
+    #[test]
+    fn zoom_steps_per_notch_and_clamps() {
+        assert!((zoomed(1.0, 1) - ZOOM_STEP).abs() < 1e-6);
+        assert!((zoomed(1.0, -2) - 1.0 / (ZOOM_STEP * ZOOM_STEP)).abs() < 1e-6);
+        assert!((zoomed(zoomed(1.0, 3), -3) - 1.0).abs() < 1e-5);
+        assert_eq!(zoomed(1.0, 0), 1.0);
+        assert_eq!(zoomed(1.0, 100), MAX_SCALE);
+        assert_eq!(zoomed(1.0, -100), MIN_SCALE);
+        assert_eq!(zoomed(MAX_SCALE, 1), MAX_SCALE);
+        assert_eq!(zoomed(MIN_SCALE, -1), MIN_SCALE);
+    }
+
+    #[test]
+    fn edges_and_corners_from_uv() {
+        assert_eq!(ResizeEdges::from_uv(0.5, 0.5, 0.03), None);
+        assert_eq!(ResizeEdges::from_uv(0.01, 0.5, 0.03), Some(edges(-1, 0)));
+        assert_eq!(ResizeEdges::from_uv(0.5, 0.99, 0.03), Some(edges(0, 1)));
+        assert_eq!(ResizeEdges::from_uv(0.01, 0.01, 0.03), Some(edges(-1, -1)));
+        assert_eq!(ResizeEdges::from_uv(0.99, 0.01, 0.03), Some(edges(1, -1)));
+        assert_eq!(ResizeEdges::from_uv(0.98, 0.98, 0.03), Some(edges(1, 1)));
+        assert!(matches!(edges(-1, -1).cursor(), ResizeType::MixedLeft));
+        assert!(matches!(edges(1, 1).cursor(), ResizeType::MixedRight));
+        assert!(matches!(edges(0, 1).cursor(), ResizeType::Vertical));
+        assert!(matches!(edges(1, 0).cursor(), ResizeType::Horizontal));
+    }
+}
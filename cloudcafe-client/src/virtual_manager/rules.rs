@@ -0,0 +1,222 @@
+use std::fs;
+use std::path::PathBuf;
+use color_eyre::{Report, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::config::config_dir;
+use crate::internal_os::WindowIdentity;
+
+const RULES_FILE: &'static str = "rules.toml";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowRule {
+    pub title: Option<String>,
+    pub class: Option<String>,
+    pub exe: Option<String>,
+    pub ignore: Option<bool>,
+    pub aspect_ratio: Option<f32>,
+    pub position: Option<[f32; 3]>,
+    pub workspace: Option<String>,
+    pub capture: Option<bool>,
+    pub always_on_top: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<WindowRule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowPolicy {
+    pub ignore: bool,
+    pub aspect_ratio: Option<f32>,
+    pub position: Option<[f32; 3]>,
+    pub workspace: Option<String>,
+    pub capture: bool,
+    pub always_on_top: bool,
+}
+impl Default for WindowPolicy {
+    fn default() -> Self {
+        Self {
+            ignore: false,
+            aspect_ratio: None,
+            position: None,
+            workspace: None,
+            capture: true,
+            always_on_top: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CompiledRule {
+    rule: WindowRule,
+    title: Option<Regex>,
+}
+impl CompiledRule {
+    fn matches(&self, identity: &WindowIdentity) -> bool {
+        self.title.as_ref().map_or(true, |title| title.is_match(&identity.title))
+            && self.rule.class.as_ref().map_or(true, |class| *class == identity.class)
+            && self.rule.exe.as_ref().map_or(true, |exe| exe.eq_ignore_ascii_case(&identity.exe))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+impl RuleSet {
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join(RULES_FILE))
+    }
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(text).map_err(|err| Report::msg(format!("invalid {RULES_FILE}: {err}")))?;
+        Self::compile(file.rules)
+    }
+    pub fn compile(rules: Vec<WindowRule>) -> Result<Self> {
+        let mut compiled = Vec::new();
+        for (index, rule) in rules.into_iter().enumerate() {
+            if rule.title.is_none() && rule.class.is_none() && rule.exe.is_none() {
+                return Err(Report::msg(format!("rule {} in {RULES_FILE} needs a title, class or exe to match", index + 1)));
+            }
+            if let Some(aspect_ratio) = rule.aspect_ratio {
+                if !(aspect_ratio > 0.0) {
+                    return Err(Report::msg(format!("rule {} in {RULES_FILE} has an invalid aspect_ratio {aspect_ratio}", index + 1)));
+                }
+            }
+            let title = match rule.title.as_ref() {
+                None => None,
+                Some(title) => Some(Regex::new(title).map_err(|err| Report::msg(format!("rule {} in {RULES_FILE} has an invalid title pattern: {err}", index + 1)))?),
+            };
+            compiled.push(CompiledRule { rule, title });
+        }
+        Ok(Self { rules: compiled })
+    }
+    pub fn load() -> Self {
+        let text = match Self::path().and_then(|path| Ok(fs::read_to_string(path)?)) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        match Self::parse(&text) {
+            Ok(rules) => rules,
+            Err(err) => {
+                println!("ignoring window rules: {err}");
+                Self::default()
+            }
+        }
+    }
+    pub fn evaluate(&self, identity: &WindowIdentity) -> WindowPolicy {
+        let mut policy = WindowPolicy::default();
+        for compiled in self.rules.iter().filter(|compiled| compiled.matches(identity)) {
+            let rule = &compiled.rule;
+            policy.ignore = rule.ignore.unwrap_or(policy.ignore);
+            policy.aspect_ratio = rule.aspect_ratio.or(policy.aspect_ratio);
+            policy.position = rule.position.or(policy.position);
+            policy.workspace = rule.workspace.clone().or(policy.workspace);
+            policy.capture = rule.capture.unwrap_or(policy.capture);
+            policy.always_on_top = rule.always_on_top.unwrap_or(policy.always_on_top);
+        }
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(exe: &str, class: &str, title: &str) -> WindowIdentity {
+        WindowIdentity {
+            exe: exe.to_string(),
+            class: class.to_string(),
+            title: title.to_string(),
+        }
+    }
+    fn notepad() -> WindowIdentity {
+        identity("notepad.exe", "Notepad", "Untitled - Notepad")
+    }
+    fn compile_error(rules: Vec<WindowRule>) -> String {
+        RuleSet::compile(rules).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn no_rules_keeps_the_default_policy() {
+        assert_eq!(RuleSet::default().evaluate(&notepad()), WindowPolicy::default());
+    }
+
+    #[test]
+    fn later_matches_win_per_field() {
+        let rules = RuleSet::parse(r#"
+            [[rule]]
+            exe = "notepad.exe"
+            aspect_ratio = 1.5
+            workspace = "notes"
+            always_on_top = true
+
+            [[rule]]
+            class = "Notepad"
+            aspect_ratio = 2.0
+            capture = false
+
+            [[rule]]
+            exe = "code.exe"
+            workspace = "code"
+        "#).unwrap();
+        let policy = rules.evaluate(&notepad());
+        assert_eq!(policy.aspect_ratio, Some(2.0));
+        assert_eq!(policy.workspace.as_deref(), Some("notes"));
+        assert!(policy.always_on_top && !policy.capture && !policy.ignore);
+        assert_eq!(policy.position, None);
+    }
+
+    #[test]
+    fn matches_title_class_and_exe() {
+        let rule = |title: Option<&str>, class: Option<&str>, exe: Option<&str>| WindowRule {
+            title: title.map(String::from),
+            class: class.map(String::from),
+            exe: exe.map(String::from),
+            ignore: Some(true),
+            ..WindowRule::default()
+        };
+        let ignored = |rule: WindowRule| RuleSet::compile(vec![rule]).unwrap().evaluate(&notepad()).ignore;
+        assert!(ignored(rule(Some("^Untitled - "), None, None)));
+        assert!(ignored(rule(Some("Notepad$"), None, None)));
+        assert!(!ignored(rule(Some("^Notepad"), None, None)));
+        assert!(ignored(rule(None, Some("Notepad"), None)));
+        assert!(!ignored(rule(None, Some("notepad"), None)));
+        assert!(ignored(rule(None, None, Some("NOTEPAD.EXE"))));
+        assert!(!ignored(rule(None, None, Some("code.exe"))));
+        assert!(ignored(rule(Some("Untitled"), Some("Notepad"), Some("notepad.exe"))));
+        assert!(!ignored(rule(Some("Untitled"), Some("Notepad"), Some("code.exe"))));
+    }
+
+    #[test]
+    fn ignore_can_be_overridden() {
+        let rules = RuleSet::parse(r#"
+            [[rule]]
+            exe = "notepad.exe"
+            ignore = true
+
+            [[rule]]
+            title = "^Untitled"
+            ignore = false
+        "#).unwrap();
+        assert!(!rules.evaluate(&notepad()).ignore);
+        assert!(rules.evaluate(&identity("notepad.exe", "Notepad", "notes.txt - Notepad")).ignore);
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        let matcher = || WindowRule {
+            exe: Some(String::from("notepad.exe")),
+            ..WindowRule::default()
+        };
+        assert!(compile_error(vec![matcher(), WindowRule { ignore: Some(true), ..WindowRule::default() }]).contains("rule 2"));
+        assert!(compile_error(vec![WindowRule { title: Some(String::from("(unclosed")), ..WindowRule::default() }]).contains("invalid title pattern"));
+        for aspect_ratio in [0.0, -1.0, f32::NAN] {
+            assert!(compile_error(vec![WindowRule { aspect_ratio: Some(aspect_ratio), ..matcher() }]).contains("invalid aspect_ratio"), "{aspect_ratio}");
+        }
+        assert!(RuleSet::parse("rule = 5").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::virtual_manager::tiling::{Footprint, wrap_angle};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SnapSettings {
+    pub slot_angle: f32,
+    pub angle_threshold: f32,
+    pub height_threshold: f32,
+    pub horizon_height: f32,
+}
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            slot_angle: 15_f32.to_radians(),
+            angle_threshold: 2_f32.to_radians(),
+            height_threshold: 0.03,
+            horizon_height: 0.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SnapTarget {
+    Slot,
+    Horizon,
+    NeighbourTop,
+    NeighbourBottom,
+    NeighbourSide,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Snap {
+    pub footprint: Footprint,
+    pub theta: Option<SnapTarget>,
+    pub height: Option<SnapTarget>,
+}
+impl Snap {
+    pub fn snapped(&self) -> bool {
+        self.theta.is_some() || self.height.is_some()
+    }
+}
+
+fn nearest(current: f32, candidates: &[(f32, SnapTarget)], threshold: f32, distance: impl Fn(f32, f32) -> f32) -> Option<(f32, SnapTarget)> {
+    candidates.iter()
+        .map(|(value, target)| (distance(current, *value), *value, *target))
+        .filter(|(dist, _, _)| *dist <= threshold)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, value, target)| (value, target))
+}
+
+pub fn snap(dragged: Footprint, neighbours: &[Footprint], settings: &SnapSettings) -> Snap {
+    let mut heights = vec![(settings.horizon_height, SnapTarget::Horizon)];
+    let mut thetas = vec![((dragged.theta / settings.slot_angle).round() * settings.slot_angle, SnapTarget::Slot)];
+    for neighbour in neighbours {
+        let top = neighbour.height + neighbour.world_height / 2.0;
+        let bottom = neighbour.height - neighbour.world_height / 2.0;
+        heights.push((top - dragged.world_height / 2.0, SnapTarget::NeighbourTop));
+        heights.push((bottom + dragged.world_height / 2.0, SnapTarget::NeighbourBottom));
+        if (neighbour.height - dragged.height).abs() < (neighbour.world_height + dragged.world_height) / 2.0 {
+            let half_width = (neighbour.angular_width + dragged.angular_width) / 2.0;
+            thetas.push((neighbour.theta + half_width, SnapTarget::NeighbourSide));
+            thetas.push((neighbour.theta - half_width, SnapTarget::NeighbourSide));
+        }
+    }
+    let height = nearest(dragged.height, &heights, settings.height_threshold, |a, b| (a - b).abs());
+    let theta = nearest(dragged.theta, &thetas, settings.angle_threshold, |a, b| wrap_angle(a - b).abs());
+    let mut footprint = dragged;
+    if let Some((value, _)) = height {
+        footprint.height = value;
+    }
+    if let Some((value, _)) = theta {
+        footprint.theta = wrap_angle(value);
+    }
+    Snap {
+        footprint,
+        theta: theta.map(|(_, target)| target),
+        height: height.map(|(_, target)| target),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, PI};
+    use super::*;
+
+    const RADIUS: f32 = 1.3;
+    const WIDTH: f32 = 0.8;
+    const HEIGHT: f32 = 0.5;
+    const EPSILON: f32 = 0.0001;
+
+    fn footprint(theta: f32, height: f32) -> Footprint {
+        Footprint::new(theta, height, WIDTH, HEIGHT, RADIUS)
+    }
+
+    #[test]
+    fn snaps_to_slots_and_the_horizon() {
+        let settings = SnapSettings::default();
+        let snapped = snap(footprint(settings.slot_angle * 3.0 + 1_f32.to_radians(), 0.02), &[], &settings);
+        assert_eq!((snapped.theta, snapped.height), (Some(SnapTarget::Slot), Some(SnapTarget::Horizon)));
+        assert!((snapped.footprint.theta - settings.slot_angle * 3.0).abs() < EPSILON && snapped.footprint.height == settings.horizon_height);
+        let front = snap(footprint(-FRAC_PI_2, 0.0), &[], &settings);
+        assert!((front.footprint.theta + FRAC_PI_2).abs() < EPSILON, "{front:?}");
+    }
+
+    #[test]
+    fn misses_outside_the_thresholds() {
+        let settings = SnapSettings::default();
+        let dragged = footprint(settings.slot_angle * 3.0 + 5_f32.to_radians(), 0.2);
+        let snapped = snap(dragged, &[footprint(1.0, -0.9)], &settings);
+        assert!(!snapped.snapped(), "{snapped:?}");
+        assert_eq!(snapped.footprint, dragged);
+    }
+
+    #[test]
+    fn lines_up_with_neighbour_edges() {
+        let settings = SnapSettings::default();
+        let neighbour = footprint(-FRAC_PI_2 + 1.0, 0.1);
+        let top = snap(footprint(-FRAC_PI_2, 0.11), &[neighbour], &settings);
+        assert_eq!(top.height, Some(SnapTarget::NeighbourTop));
+        assert!((top.footprint.height - 0.1).abs() < EPSILON);
+        let tall = Footprint::new(-FRAC_PI_2 + 1.0, 0.1, WIDTH, 1.0, RADIUS);
+        let top = snap(footprint(-FRAC_PI_2, 0.36), &[tall], &settings);
+        assert_eq!(top.height, Some(SnapTarget::NeighbourTop));
+        assert!((top.footprint.height - 0.35).abs() < EPSILON, "{top:?}");
+        let bottom = snap(footprint(-FRAC_PI_2, -0.14), &[tall], &settings);
+        assert_eq!(bottom.height, Some(SnapTarget::NeighbourBottom));
+        assert!((bottom.footprint.height + 0.15).abs() < EPSILON, "{bottom:?}");
+    }
+
+    #[test]
+    fn sits_beside_neighbours_on_the_same_row() {
+        let settings = SnapSettings::default();
+        let neighbour = footprint(-FRAC_PI_2 + 0.05, 0.1);
+        let side = neighbour.theta + neighbour.angular_width + 0.01;
+        let snapped = snap(footprint(side, 0.2), &[neighbour], &settings);
+        assert_eq!(snapped.theta, Some(SnapTarget::NeighbourSide));
+        assert!((snapped.footprint.theta - (neighbour.theta + neighbour.angular_width)).abs() < EPSILON, "{snapped:?}");
+        let below = footprint(-FRAC_PI_2 + 0.05, -1.0);
+        assert_ne!(snap(footprint(side, 0.2), &[below], &settings).theta, Some(SnapTarget::NeighbourSide));
+    }
+
+    #[test]
+    fn wraps_around_behind_the_user() {
+        let settings = SnapSettings::default();
+        let snapped = snap(footprint(-PI + 0.01, 0.5), &[], &settings);
+        assert_eq!(snapped.theta, Some(SnapTarget::Slot));
+        assert_eq!(snapped.footprint.theta, PI);
+        let neighbour = footprint(PI - 0.2, 0.5);
+        let side = wrap_angle(neighbour.theta + neighbour.angular_width + 0.01);
+        let snapped = snap(footprint(side, 0.5), &[neighbour], &settings);
+        assert_eq!(snapped.theta, Some(SnapTarget::NeighbourSide));
+        assert!((snapped.footprint.theta - wrap_angle(neighbour.theta + neighbour.angular_width)).abs() < EPSILON, "{snapped:?}");
+    }
+}
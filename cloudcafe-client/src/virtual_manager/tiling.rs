@@ -0,0 +1,268 @@
+use std::f32::consts::{PI, TAU};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::values::{cart_2_cyl, cyl_2_cart};
+
+const EPSILON: f32 = 0.0001;
+const FRONT_SPREAD: f32 = PI / 2.0;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TilingStrategy {
+    #[default]
+    Fill,
+    Grid,
+    Cascade,
+    Column,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Footprint {
+    pub theta: f32,
+    pub height: f32,
+    pub angular_width: f32,
+    pub world_height: f32,
+}
+impl Footprint {
+    pub fn new(theta: f32, height: f32, width: f32, world_height: f32, radius: f32) -> Self {
+        Self {
+            theta: wrap_angle(theta),
+            height,
+            angular_width: width / radius,
+            world_height,
+        }
+    }
+    pub fn from_position(position: Vec3, width: f32, world_height: f32, radius: f32) -> Self {
+        let cyl = cart_2_cyl(position);
+        Self::new(cyl.y, cyl.z, width, world_height, radius)
+    }
+    pub fn position(&self, radius: f32) -> Vec3 {
+        cyl_2_cart(Vec3::new(radius, self.theta, self.height))
+    }
+    pub fn overlaps(&self, other: &Footprint) -> bool {
+        let theta_dist = wrap_angle(self.theta - other.theta).abs();
+        let height_dist = (self.height - other.height).abs();
+        theta_dist < (self.angular_width + other.angular_width) / 2.0 && height_dist < (self.world_height + other.world_height) / 2.0
+    }
+}
+
+pub fn wrap_angle(angle: f32) -> f32 {
+    let angle = (angle + PI).rem_euclid(TAU) - PI;
+    if angle == -PI {
+        return PI;
+    }
+    angle
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TilingEngine {
+    pub strategy: TilingStrategy,
+    pub radius: f32,
+    pub gap: f32,
+}
+impl TilingEngine {
+    pub fn new(strategy: TilingStrategy, radius: f32) -> Self {
+        Self {
+            strategy,
+            radius,
+            gap: 0.02,
+        }
+    }
+    fn angular_gap(&self) -> f32 {
+        self.gap / self.radius
+    }
+    fn is_free(&self, candidate: &Footprint, existing: &[Footprint]) -> bool {
+        let padded = Footprint {
+            angular_width: candidate.angular_width + self.angular_gap() * 2.0 - EPSILON,
+            world_height: candidate.world_height + self.gap * 2.0 - EPSILON,
+            ..*candidate
+        };
+        existing.iter().all(|other| !padded.overlaps(other))
+    }
+    fn candidate(&self, theta: f32, height: f32, width: f32, world_height: f32) -> Footprint {
+        Footprint::new(theta, height, width, world_height, self.radius)
+    }
+    pub fn place(&self, gaze_theta: f32, gaze_height: f32, existing: &[Footprint], width: f32, world_height: f32) -> Footprint {
+        for spread in [FRONT_SPREAD, PI] {
+            let placed = match self.strategy {
+                TilingStrategy::Fill => self.fill(gaze_theta, gaze_height, existing, width, world_height, spread),
+                TilingStrategy::Grid => self.grid(gaze_theta, gaze_height, existing, width, world_height, spread),
+                TilingStrategy::Column => self.column(gaze_theta, gaze_height, existing, width, world_height, spread),
+                TilingStrategy::Cascade => None,
+            };
+            if let Some(placed) = placed {
+                return placed;
+            }
+        }
+        self.cascade(gaze_theta, gaze_height, existing, width, world_height)
+    }
+    fn fill(&self, gaze_theta: f32, gaze_height: f32, existing: &[Footprint], width: f32, world_height: f32, spread: f32) -> Option<Footprint> {
+        let step = (width / self.radius / 8.0).max(0.01);
+        let steps = (spread / step) as i32;
+        for row in [0.0, -1.0, 1.0] {
+            let height = gaze_height + row * (world_height + self.gap);
+            for i in 0..=steps {
+                for side in [1.0, -1.0] {
+                    let candidate = self.candidate(gaze_theta + side * i as f32 * step, height, width, world_height);
+                    if self.is_free(&candidate, existing) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+    fn grid(&self, gaze_theta: f32, gaze_height: f32, existing: &[Footprint], width: f32, world_height: f32, spread: f32) -> Option<Footprint> {
+        let cell_width = width / self.radius + self.angular_gap();
+        let cell_height = world_height + self.gap;
+        let columns = (spread / cell_width) as i32;
+        for row in [0.0, -1.0, 1.0] {
+            for column in 0..=columns {
+                for side in [1.0, -1.0] {
+                    let candidate = self.candidate(gaze_theta + side * column as f32 * cell_width, gaze_height + row * cell_height, width, world_height);
+                    if self.is_free(&candidate, existing) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+    fn column(&self, gaze_theta: f32, gaze_height: f32, existing: &[Footprint], width: f32, world_height: f32, spread: f32) -> Option<Footprint> {
+        let cell_width = width / self.radius + self.angular_gap();
+        let cell_height = world_height + self.gap;
+        let columns = (spread / cell_width) as i32;
+        for column in 0..=columns {
+            for side in [1.0, -1.0] {
+                for row in [0.0, -1.0, 1.0] {
+                    let candidate = self.candidate(gaze_theta + side * column as f32 * cell_width, gaze_height + row * cell_height, width, world_height);
+                    if self.is_free(&candidate, existing) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+    fn cascade(&self, gaze_theta: f32, gaze_height: f32, existing: &[Footprint], width: f32, world_height: f32) -> Footprint {
+        let theta_step = 0.05 / self.radius;
+        let height_step = 0.05;
+        let mut index = 0;
+        loop {
+            let candidate = self.candidate(gaze_theta + index as f32 * theta_step, gaze_height - index as f32 * height_step, width, world_height);
+            let taken = existing.iter().any(|other| wrap_angle(other.theta - candidate.theta).abs() < theta_step / 2.0 && (other.height - candidate.height).abs() < height_step / 2.0);
+            if !taken || index >= existing.len() {
+                return candidate;
+            }
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    const RADIUS: f32 = 1.3;
+    const WIDTH: f32 = 0.8;
+    const HEIGHT: f32 = 0.5;
+    const FRONT: f32 = -FRAC_PI_2;
+    const STRATEGIES: [TilingStrategy; 4] = [TilingStrategy::Fill, TilingStrategy::Grid, TilingStrategy::Column, TilingStrategy::Cascade];
+
+    fn place_many(engine: &TilingEngine, gaze_theta: f32, count: usize) -> Vec<Footprint> {
+        let mut placed = Vec::new();
+        for _ in 0..count {
+            let footprint = engine.place(gaze_theta, 0.0, &placed, WIDTH, HEIGHT);
+            placed.push(footprint);
+        }
+        placed
+    }
+    fn assert_disjoint(placed: &[Footprint]) {
+        for (index, a) in placed.iter().enumerate() {
+            for b in &placed[index + 1..] {
+                assert!(!a.overlaps(b), "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+    fn full_ring() -> Vec<Footprint> {
+        let step = WIDTH / RADIUS;
+        let columns = (TAU / step).ceil() as i32;
+        (-2..=2).flat_map(|row| (0..columns).map(move |column| Footprint::new(column as f32 * step, row as f32 * HEIGHT, WIDTH, HEIGHT, RADIUS))).collect()
+    }
+
+    #[test]
+    fn first_window_goes_where_the_user_looks() {
+        for strategy in STRATEGIES {
+            let placed = TilingEngine::new(strategy, RADIUS).place(FRONT, 0.2, &[], WIDTH, HEIGHT);
+            assert!((placed.theta - FRONT).abs() < EPSILON && (placed.height - 0.2).abs() < EPSILON, "{strategy:?} {placed:?}");
+        }
+    }
+
+    #[test]
+    fn fill_packs_next_to_existing_windows() {
+        let engine = TilingEngine::new(TilingStrategy::Fill, RADIUS);
+        let placed = place_many(&engine, FRONT, 8);
+        assert_disjoint(&placed);
+        let second = placed[1];
+        assert_eq!(second.height, 0.0);
+        let expected = (WIDTH + engine.gap) / RADIUS;
+        assert!((second.theta - FRONT).abs() >= expected - EPSILON && (second.theta - FRONT).abs() < expected + WIDTH / RADIUS / 8.0, "{second:?}");
+    }
+
+    #[test]
+    fn grid_snaps_to_cells() {
+        let engine = TilingEngine::new(TilingStrategy::Grid, RADIUS);
+        let placed = place_many(&engine, FRONT, 8);
+        assert_disjoint(&placed);
+        let cell_width = WIDTH / RADIUS + engine.angular_gap();
+        let cell_height = HEIGHT + engine.gap;
+        for footprint in &placed {
+            let columns = wrap_angle(footprint.theta - FRONT) / cell_width;
+            let rows = footprint.height / cell_height;
+            assert!((columns - columns.round()).abs() < 1e-3 && (rows - rows.round()).abs() < 1e-3, "{footprint:?}");
+        }
+        assert!(placed[..5].iter().all(|footprint| footprint.height == 0.0) && placed[5].height < 0.0);
+    }
+
+    #[test]
+    fn column_fills_rows_before_moving_sideways() {
+        let engine = TilingEngine::new(TilingStrategy::Column, RADIUS);
+        let placed = place_many(&engine, FRONT, 4);
+        assert_disjoint(&placed);
+        let row = HEIGHT + engine.gap;
+        assert_eq!(placed.iter().map(|footprint| (footprint.height / row).round() as i32).collect::<Vec<_>>(), vec![0, -1, 1, 0]);
+        assert!((placed[1].theta - FRONT).abs() < EPSILON && (placed[3].theta - FRONT).abs() > EPSILON);
+    }
+
+    #[test]
+    fn wraps_around_behind_the_user() {
+        for strategy in [TilingStrategy::Fill, TilingStrategy::Grid, TilingStrategy::Column] {
+            let placed = place_many(&TilingEngine::new(strategy, RADIUS), PI - 0.1, 8);
+            assert_disjoint(&placed);
+            assert!(placed.iter().all(|footprint| footprint.theta > -PI && footprint.theta <= PI));
+            assert!(placed.iter().any(|footprint| footprint.theta < 0.0), "{strategy:?} never crossed -pi");
+        }
+        assert_eq!(wrap_angle(-PI), PI);
+        assert!((wrap_angle(PI + 0.5) - (-PI + 0.5)).abs() < EPSILON);
+        let a = Footprint::new(PI - 0.05, 0.0, WIDTH, HEIGHT, RADIUS);
+        let b = Footprint::new(-PI + 0.05, 0.0, WIDTH, HEIGHT, RADIUS);
+        assert!(a.overlaps(&b));
+    }
+
+    #[test]
+    fn cascade_when_the_ring_is_full() {
+        let existing = full_ring();
+        for strategy in STRATEGIES {
+            let engine = TilingEngine::new(strategy, RADIUS);
+            let placed = engine.place(FRONT, 0.0, &existing, WIDTH, HEIGHT);
+            assert_eq!(placed, engine.cascade(FRONT, 0.0, &existing, WIDTH, HEIGHT), "{strategy:?}");
+        }
+        let engine = TilingEngine::new(TilingStrategy::Cascade, RADIUS);
+        let placed = place_many(&engine, FRONT, 3);
+        for (index, footprint) in placed.iter().enumerate() {
+            assert!((footprint.theta - (FRONT + index as f32 * 0.05 / RADIUS)).abs() < EPSILON, "{footprint:?}");
+            assert!((footprint.height + index as f32 * 0.05).abs() < EPSILON, "{footprint:?}");
+        }
+    }
+}
@@ -15,6 +15,8 @@ use crate::values::{cart_2_cyl, cart_2_sphere, cyl_2_cart, quat_lookat, sphere_2
 
 const POINT_MODEL: &[u8] = include_bytes!("..\\..\\assets\\mouse.glb");
 const RESIZE_MODEL: &[u8] = include_bytes!("..\\..\\assets\\resize_cursor.glb");
+// below this height relative to the center the cursor leaves the cylinder for the floor sphere
+pub const FLOOR_HEIGHT: f32 = -0.7;
 
 pub struct VMouse {
     mouse_cursor: MouseCursor,
@@ -128,11 +130,12 @@ impl VMouse {
                 x_sensitivity: 1000.0,
             })
     }
-    pub fn update_pos(&mut self, dx: i32, dy: i32) {
+    pub fn update_pos(&mut self, dx: i32, dy: i32, center: Vec3) {
         if dx == 0 && dy == 0 {
             return;
         }
-        if self.pos.y > -0.7 {
+        self.pos -= center;
+        if self.pos.y > FLOOR_HEIGHT {
             let mut cyl_pos = cart_2_cyl(self.pos);
             cyl_pos.y += dx as f32 / self.x_sensitivity;
             cyl_pos.z += -dy as f32 / self.y_sensitivity;
@@ -147,7 +150,7 @@ impl VMouse {
             self.pos = pos;
         }
         else {
-            self.pos.y += 0.7;
+            self.pos.y -= FLOOR_HEIGHT;
             let mut cyl_pos = cart_2_sphere(self.pos);
             cyl_pos.z += dx as f32 / self.x_sensitivity;
             cyl_pos.y += dy as f32 / self.y_sensitivity;
@@ -161,9 +164,10 @@ impl VMouse {
                 cyl_pos.y = PI - (PI * 0.35);
             }
             let mut pos = sphere_2_cart(cyl_pos);
-            pos.y -= 0.7;
+            pos.y += FLOOR_HEIGHT;
             self.pos = pos;
         }
+        self.pos += center;
     }
     pub fn draw(&self, sk: &StereoKitDraw, center: Vec3) {
         let quat = quat_lookat(center, self.pos);
@@ -21,7 +21,10 @@ use stereokit::values::{Color128, Color32};
 use windows::Win32::Foundation::HWND;
 use crate::internal_os::FakeMonitor;
 use crate::values::{IVec2, UVec2};
+use crate::virtual_manager::anchor::Anchor;
+use crate::virtual_manager::curvature::Bend;
 use crate::virtual_manager::desktop_capture::CaptureDesktop;
+use crate::virtual_manager::resize::METERS_PER_PIXEL;
 
 pub struct VWindow {
     pub window_capture: Option<WindowCapture>,
@@ -31,6 +34,10 @@ pub struct VWindow {
     hwnd: Hwnd,
     pub(crate) pose: Pose,
     pub z_depth: u32,
+    pub scale: f32,
+    pub anchor: Anchor,
+    pub capture_enabled: bool,
+    pub always_on_top: bool,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -52,13 +59,35 @@ impl VWindow {
             hwnd,
             pose,
             z_depth,
+            scale: 1.0,
+            anchor: Anchor::default(),
+            capture_enabled: true,
+            always_on_top: false,
         })
     }
-    pub fn draw(&mut self, sk: &StereoKitDraw, radius: f32, focused: bool) -> IsWindowValid {
+    pub fn set_monitor(&mut self, fake_monitor: FakeMonitor, capture_desktop: CaptureDesktop) {
+        self.internal_window.set_monitor(fake_monitor);
+        self.capture_desktop = capture_desktop;
+    }
+    pub fn suspend(&mut self) {
+        drop(self.window_capture.take());
+    }
+    pub fn is_valid(&self) -> bool {
+        is_window(self.hwnd)
+    }
+    pub fn draw(&mut self, sk: &StereoKitDraw, radius: f32, curvature: f32, focused: bool) -> IsWindowValid {
         if !is_window(self.hwnd) {
             return IsWindowValid::Invalid
         }
-        if self.internal_window.size_changed() {
+        if !self.capture_enabled {
+            if self.internal_window.size_changed() && self.internal_window.update_size().is_none() {
+                return IsWindowValid::Invalid;
+            }
+            self.grab_bar.draw(sk, self.grab_bar_matrix(), self.queue_offset());
+            self.grab_bar.draw_button(sk, self.minimize_matrix());
+            return IsWindowValid::Valid;
+        }
+        if self.internal_window.size_changed() || self.window_capture.is_none() {
             if self.internal_window.size().unwrap().x == 0 || self.internal_window.size().unwrap().y == 0 {
                 return IsWindowValid::Invalid;
             }
@@ -73,30 +102,61 @@ impl VWindow {
                 return IsWindowValid::Invalid;
             }
         }
-        self.update_radius(radius);
+        self.update_radius(sk, radius, curvature);
         if let Some(window_capture) = self.window_capture.as_ref() {
-            window_capture.material.set_queue_offset(sk, self.z_depth as i32 - 200);
+            window_capture.material.set_queue_offset(sk, self.queue_offset());
             window_capture.get_model(focused, &self.capture_desktop).draw(sk, self.matrix().unwrap().into(), Color128::new_rgb(1.0, 1.0, 1.0), RenderLayer::Layer1);
-            self.grab_bar.draw(sk, self.grab_bar_matrix(), self.z_depth as i32 - 200);
+            self.grab_bar.draw(sk, self.grab_bar_matrix(), self.queue_offset());
+            self.grab_bar.draw_button(sk, self.minimize_matrix());
         }
         return IsWindowValid::Valid;
     }
+    fn queue_offset(&self) -> i32 {
+        match self.always_on_top {
+            true => self.z_depth as i32 - 100,
+            false => self.z_depth as i32 - 200,
+        }
+    }
+    // windows kept on top stack above every other window regardless of z_depth
+    pub fn stacking(&self) -> (bool, u32) {
+        (self.always_on_top, self.z_depth)
+    }
+    pub fn world_size(&self) -> Option<Vec2> {
+        let size = self.internal_window.size()?;
+        Some(Vec2::new(size.x as f32, size.y as f32) * METERS_PER_PIXEL * self.scale)
+    }
     pub fn grab_bar_matrix(&self) -> Mat4 {
-        let size = self.internal_window.size().unwrap();
-        self.grab_bar.gen_matrix(self.pose, size.x as f32 * 0.0005, size.y as f32 * 0.0005)
+        let size = self.world_size().unwrap();
+        self.grab_bar.gen_matrix(self.pose, size.x, size.y)
     }
-    pub fn update_radius(&mut self, radius: f32) {
-
+    pub fn minimize_matrix(&self) -> Mat4 {
+        let size = self.world_size().unwrap();
+        self.grab_bar.gen_button_matrix(self.pose, size.x, size.y)
+    }
+    pub fn update_radius(&mut self, sk: &StereoKitDraw, radius: f32, curvature: f32) {
+        let bend = match self.world_size() {
+            None => return,
+            Some(size) => Bend::new(size.x, radius, curvature),
+        };
+        if let Some(window_capture) = self.window_capture.as_mut() {
+            if window_capture.bend != bend {
+                if let Err(err) = window_capture.set_bend(sk, bend) {
+                    println!("unable to bend window mesh: {err}");
+                }
+            }
+        }
     }
     pub fn matrix(&self) -> Option<Mat4> {
         let scale = self.window_capture.as_ref()?.scale();
-        Some(self.pose.pose_matrix(Vec3::new(scale.x * 0.5, scale.y * 0.5, 1.0)).into())
+        Some(self.pose.pose_matrix(Vec3::new(scale.x * 0.5 * self.scale, scale.y * 0.5 * self.scale, 1.0)).into())
     }
     fn send_msg_recapture_window() {
         let _ = thread::spawn(|| native_dialog::MessageDialog::new().set_text("unable to recapture changed window").show_alert().unwrap());
     }
 }
 
+const GRAB_BAR_HEIGHT: f32 = 0.025;
+
 pub struct GrabBar {
     pub(crate) model: Model,
     mesh: Mesh,
@@ -118,8 +178,16 @@ impl GrabBar {
         self.material.set_queue_offset(sk, queue_offset);
         self.model.draw(sk, matrix.into(), color_named::BURLY_WOOD, RenderLayer::Layer1);
     }
+    pub fn draw_button(&self, sk: &StereoKitDraw, matrix: Mat4) {
+        self.model.draw(sk, matrix.into(), color_named::INDIAN_RED, RenderLayer::Layer1);
+    }
+    pub fn gen_button_matrix(&self, pose: Pose, window_width: f32, window_height: f32) -> Mat4 {
+        let bar_center = self.gen_matrix(pose, window_width, window_height).transform_point3(Vec3::ZERO);
+        let offset = Quat::from(pose.orientation).mul_vec3(Vec3::new((window_width - GRAB_BAR_HEIGHT) / 2.0, 0.0, 0.0));
+        Mat4::from_scale_rotation_translation(Vec3::new(GRAB_BAR_HEIGHT, GRAB_BAR_HEIGHT, 1.0), pose.orientation.into(), bar_center + offset)
+    }
     pub fn gen_matrix(&self, mut pose: Pose, window_width: f32, window_height: f32) -> Mat4 {
-        let height = 0.025;
+        let height = GRAB_BAR_HEIGHT;
         let pos = Mat4::from_translation(Quat::from(pose.orientation).mul_vec3(Vec3::new(0.0, height, 0.0)));
 
         pose.position.y += window_height / 2.0;
@@ -137,6 +205,8 @@ pub struct WindowCapture {
     pub model: Model,
     material: Material,
     size: Vec2,
+    crop: (f32, f32, f32, f32),
+    bend: Bend,
 }
 
 impl WindowCapture {
@@ -155,7 +225,8 @@ impl WindowCapture {
         material.set_queue_offset(sk, -1);
         let size = Vec2::new(window_size.x as f32 * 0.001, window_size.y as f32 * 0.001);
         //let mesh = Mesh::gen_plane(sk, size, Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 1)?;
-        let mesh = Self::gen_mesh(sk, 0.0, 0.0, 1.0, 1.0)?;
+        let crop = (0.0, 0.0, 1.0, 1.0);
+        let mesh = Self::gen_mesh(sk, crop, Bend::FLAT)?;
         let model = Model::from_mesh(sk, &mesh, &material).wrap_err("mesh")?;
 
         Ok(Self {
@@ -166,6 +237,8 @@ impl WindowCapture {
             model,
             material,
             size,
+            crop,
+            bend: Bend::FLAT,
         })
     }
     pub fn delete(self) {}
@@ -176,45 +249,53 @@ impl WindowCapture {
     pub fn scale(&self) -> Vec2 {
         self.size
     }
-    pub fn gen_mesh(sk: &impl StereoKitContext, bottom_left: f32, bottom_right: f32, top_left: f32, top_right: f32) -> Result<Mesh> {
+    pub fn gen_mesh(sk: &impl StereoKitContext, crop: (f32, f32, f32, f32), bend: Bend) -> Result<Mesh> {
+        let (left, top, right, bottom) = crop;
         let mesh = Mesh::create(sk)?;
         let mut verts = vec![];
         let mut inds = vec![];
 
-        verts.push(Vertex {
-            pos: Vec3::new(-0.5, -0.5, 0.0).into(),
-            norm: Vec3::new(0.0, 0.0, 1.0).into(),
-            uv: Vec2::new(bottom_left, top_right).into(),
-            col: Color32::from(WHITE),
-        });
-        verts.push(Vertex {
-            pos: Vec3::new(-0.5, 0.5, 0.0).into(),
-            norm: Vec3::new(0.0, 0.0, 1.0).into(),
-            uv: Vec2::new(bottom_left, bottom_right).into(),
-            col: Color32::from(WHITE),
-        });
-        verts.push(Vertex {
-            pos: Vec3::new(0.5, 0.5, 0.0).into(),
-            norm: Vec3::new(0.0, 0.0, 1.0).into(),
-            uv: Vec2::new(top_left, bottom_right).into(),
-            col: Color32::from(WHITE),
-        });
-        inds.push(2);
-        inds.push(1);
-        inds.push(0);
-        verts.push(Vertex {
-            pos: Vec3::new(0.5, -0.5, 0.0).into(),
-            norm: Vec3::new(0.0, 0.0, 1.0).into(),
-            uv: Vec2::new(top_left, top_right).into(),
-            col: Color32::from(WHITE),
-        });
-        inds.push(3);
-        inds.push(2);
-        inds.push(0);
+        let segments = bend.segments();
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let tex_u = left + (right - left) * u;
+            for (v, tex_v) in [(1.0, bottom), (0.0, top)] {
+                verts.push(Vertex {
+                    pos: bend.surface_point(Vec2::new(u, v)).into(),
+                    norm: bend.surface_normal(u).into(),
+                    uv: Vec2::new(tex_u, tex_v).into(),
+                    col: Color32::from(WHITE),
+                });
+            }
+            if i > 0 {
+                let (bottom_left, top_left, bottom_right, top_right) = (i * 2 - 2, i * 2 - 1, i * 2, i * 2 + 1);
+                inds.extend([top_right, top_left, bottom_left]);
+                inds.extend([bottom_right, top_right, bottom_left]);
+            }
+        }
         mesh.set_verts(sk, &verts, true);
         mesh.set_indices(sk, &inds);
         Ok(mesh)
     }
+    fn rebuild_mesh(&mut self, sk: &impl StereoKitContext) -> Result<()> {
+        self.mesh = Self::gen_mesh(sk, self.crop, self.bend)?;
+        self.model.set_mesh(sk, 0, &self.mesh);
+        Ok(())
+    }
+    pub fn set_crop(&mut self, sk: &impl StereoKitContext, crop: (f32, f32, f32, f32)) -> Result<()> {
+        self.crop = crop;
+        self.rebuild_mesh(sk)
+    }
+    pub fn set_bend(&mut self, sk: &impl StereoKitContext, bend: Bend) -> Result<()> {
+        self.bend = bend;
+        self.rebuild_mesh(sk)
+    }
+    pub fn uv_at(&self, local: Vec3) -> Vec2 {
+        self.bend.surface_uv(local)
+    }
+    pub fn local_at(&self, uv: Vec2) -> Vec3 {
+        self.bend.surface_point(uv)
+    }
     pub fn get_mesh(&self) -> &Mesh {
         &self.mesh
     }
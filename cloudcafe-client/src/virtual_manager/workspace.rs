@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+pub type WorkspaceId = usize;
+
+const DEFAULT_WORKSPACES: usize = 4;
+
+pub struct Workspaces {
+    names: Vec<String>,
+    current: WorkspaceId,
+    members: HashMap<isize, WorkspaceId>,
+}
+impl Workspaces {
+    pub fn new(names: &[String]) -> Self {
+        let mut names = names.iter().filter(|name| !name.trim().is_empty()).cloned().collect::<Vec<_>>();
+        if names.is_empty() {
+            names = (1..=DEFAULT_WORKSPACES).map(|index| index.to_string()).collect();
+        }
+        Self {
+            names,
+            current: 0,
+            members: HashMap::new(),
+        }
+    }
+    pub fn current(&self) -> WorkspaceId {
+        self.current
+    }
+    pub fn name(&self, workspace: WorkspaceId) -> &str {
+        &self.names[workspace]
+    }
+    pub fn find(&self, name: &str) -> Option<WorkspaceId> {
+        self.names.iter().position(|existing| existing == name)
+    }
+    pub fn neighbour(&self, workspace: WorkspaceId, offset: isize) -> WorkspaceId {
+        (workspace as isize + offset).rem_euclid(self.names.len() as isize) as WorkspaceId
+    }
+    pub fn switch_to(&mut self, workspace: WorkspaceId) -> bool {
+        if workspace >= self.names.len() || workspace == self.current {
+            return false;
+        }
+        self.current = workspace;
+        true
+    }
+    pub fn assign(&mut self, id: isize, workspace: Option<WorkspaceId>) -> WorkspaceId {
+        let workspace = workspace.filter(|workspace| *workspace < self.names.len()).unwrap_or(self.current);
+        self.members.insert(id, workspace);
+        workspace
+    }
+    pub fn workspace_of(&self, id: isize) -> Option<WorkspaceId> {
+        self.members.get(&id).copied()
+    }
+    pub fn is_visible(&self, id: isize) -> bool {
+        // every tracked window is assigned when it is created, anything else belongs to no workspace
+        self.workspace_of(id) == Some(self.current)
+    }
+    pub fn move_window(&mut self, id: isize, workspace: WorkspaceId) -> bool {
+        if workspace >= self.names.len() || !self.members.contains_key(&id) {
+            return false;
+        }
+        self.members.insert(id, workspace);
+        true
+    }
+    pub fn remove(&mut self, id: isize) {
+        self.members.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(names: &[&str]) -> Workspaces {
+        Workspaces::new(&names.iter().map(|name| name.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn defaults_and_names() {
+        let workspaces = named(&["", "  "]);
+        assert_eq!((0..DEFAULT_WORKSPACES).map(|workspace| workspaces.name(workspace)).collect::<Vec<_>>(), ["1", "2", "3", "4"]);
+        let workspaces = named(&["code", "", "chat"]);
+        assert_eq!(workspaces.find("chat"), Some(1));
+        assert_eq!(workspaces.find("mail"), None);
+    }
+
+    #[test]
+    fn neighbour_wraps_around() {
+        let workspaces = named(&["a", "b", "c"]);
+        assert_eq!(workspaces.neighbour(0, 1), 1);
+        assert_eq!(workspaces.neighbour(2, 1), 0);
+        assert_eq!(workspaces.neighbour(0, -1), 2);
+        assert_eq!(workspaces.neighbour(1, -4), 0);
+    }
+
+    #[test]
+    fn switching_changes_what_is_visible() {
+        let mut workspaces = named(&["a", "b"]);
+        assert_eq!(workspaces.assign(10, None), 0);
+        assert_eq!(workspaces.assign(20, Some(1)), 1);
+        assert_eq!(workspaces.assign(30, Some(7)), 0);
+        assert!(workspaces.is_visible(10) && !workspaces.is_visible(20));
+        assert!(!workspaces.switch_to(0));
+        assert!(!workspaces.switch_to(2));
+        assert!(workspaces.switch_to(1));
+        assert_eq!(workspaces.current(), 1);
+        assert!(!workspaces.is_visible(10) && workspaces.is_visible(20));
+        assert_eq!(workspaces.assign(40, None), 1);
+    }
+
+    #[test]
+    fn moving_away_hides_the_window() {
+        let mut workspaces = named(&["a", "b"]);
+        workspaces.assign(10, None);
+        assert!(workspaces.move_window(10, 1));
+        assert!(!workspaces.is_visible(10));
+        assert_eq!(workspaces.workspace_of(10), Some(1));
+        assert!(!workspaces.move_window(10, 2));
+        assert!(!workspaces.move_window(99, 1));
+        workspaces.remove(10);
+        assert_eq!(workspaces.workspace_of(10), None);
+    }
+
+    #[test]
+    fn unknown_windows_are_not_visible() {
+        let mut workspaces = named(&["a", "b"]);
+        assert!(!workspaces.is_visible(99));
+        workspaces.switch_to(1);
+        assert!(!workspaces.is_visible(99));
+    }
+}
@@ -1,14 +1,21 @@
 use std::ffi::{c_void, OsStr, OsString};
+use std::fs::File;
 use std::mem::size_of;
-use std::os::windows::prelude::OsStringExt;
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::os::windows::prelude::{OsStrExt, OsStringExt};
+use std::path::Path;
 use std::string::FromUtf16Error;
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{COLORREF, HWND, POINT, RECT};
+use windows::Win32::Foundation::{COLORREF, HANDLE, HWND, POINT, RECT};
 use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
-use windows::Win32::Graphics::Gdi::{GetDC, HDC};
+use windows::Win32::Graphics::Gdi::{DISPLAY_DEVICEW, EnumDisplayDevicesW, GetDC, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW};
+use windows::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+use windows::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use windows::Win32::Storage::FileSystem::{CREATE_NEW, CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_GENERIC_WRITE, FILE_SHARE_NONE};
 use windows::Win32::System::Console::GetConsoleWindow;
+use windows::Win32::System::Memory::{HLOCAL, LocalFree};
 use windows::Win32::UI::Input::KeyboardAndMouse::IsWindowEnabled;
-use windows::Win32::UI::WindowsAndMessaging::{FindWindowW, GetClassNameW, GetCursorPos, GetWindow, GetWindowRect, GW_CHILD, GW_HWNDNEXT, IsWindow, IsWindowVisible, MoveWindow, SetCursorPos};
+use windows::Win32::UI::WindowsAndMessaging::{FindWindowW, GA_ROOT, GetAncestor, GetClassNameW, GetCursorPos, GetWindow, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, GW_CHILD, GW_HWNDNEXT, IsWindow, IsWindowVisible, MoveWindow, SetCursorPos};
 use crate::values::UVec2;
 
 pub type Hwnd = HWND;
@@ -87,6 +94,14 @@ pub fn is_window(hwnd: Hwnd) -> bool {
     println!("exit: is_window");
     ret
 }
+pub fn is_top_level(hwnd: Hwnd) -> bool {
+    println!("enter: is_top_level");
+    let ret = unsafe {
+        GetAncestor(hwnd, GA_ROOT)
+    } == hwnd;
+    println!("exit: is_top_level");
+    ret
+}
 pub fn window_visible(hwnd: Hwnd) -> bool {
     println!("enter: window_visible");
     let ret = unsafe {
@@ -157,4 +172,112 @@ pub fn get_pixel(dc: HDC, x: i32, y: i32) -> COLORREF {
     };
     println!("exit: get_pixel");
     ret
-}
\ No newline at end of file
+}
+fn from_wide(buf: &[u16]) -> String {
+    let length = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..length])
+}
+pub struct MonitorDevice {
+    pub device_name: String,
+    pub primary: bool,
+}
+pub fn monitor_device(handle: HMONITOR) -> Option<MonitorDevice> {
+    println!("enter: monitor_device");
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+    let ok = unsafe {
+        GetMonitorInfoW(handle, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO)
+    }.as_bool();
+    println!("exit: monitor_device");
+    if !ok {
+        return None;
+    }
+    Some(MonitorDevice {
+        device_name: from_wide(&info.szDevice),
+        primary: info.monitorInfo.dwFlags & 1 != 0,
+    })
+}
+pub struct DisplayDevice {
+    pub adapter_name: String,
+    pub adapter_id: String,
+    pub monitor_id: String,
+}
+pub fn display_device(device_name: &str) -> Option<DisplayDevice> {
+    println!("enter: display_device");
+    let mut index = 0;
+    let ret = loop {
+        let mut adapter = DISPLAY_DEVICEW::default();
+        adapter.cb = size_of::<DISPLAY_DEVICEW>() as u32;
+        if !unsafe { EnumDisplayDevicesW(PCWSTR::null(), index, &mut adapter, 0) }.as_bool() {
+            break None;
+        }
+        index += 1;
+        if !from_wide(&adapter.DeviceName).eq_ignore_ascii_case(device_name) {
+            continue;
+        }
+        let mut monitor = DISPLAY_DEVICEW::default();
+        monitor.cb = size_of::<DISPLAY_DEVICEW>() as u32;
+        let monitor_id = match unsafe { EnumDisplayDevicesW(PCWSTR::from_raw(adapter.DeviceName.as_ptr()), 0, &mut monitor, 0) }.as_bool() {
+            true => from_wide(&monitor.DeviceID),
+            false => String::new(),
+        };
+        break Some(DisplayDevice {
+            adapter_name: from_wide(&adapter.DeviceString),
+            adapter_id: from_wide(&adapter.DeviceID),
+            monitor_id,
+        });
+    };
+    println!("exit: display_device");
+    ret
+}
+pub fn window_title(hwnd: Hwnd) -> Option<String> {
+    println!("enter: window_title");
+    const MAX_TITLE_LENGTH: usize = 512;
+    let mut title = [0u16; MAX_TITLE_LENGTH];
+    let length = unsafe {
+        GetWindowTextW(hwnd, &mut title)
+    };
+    println!("exit: window_title");
+    if length <= 0 {
+        return None;
+    }
+    String::from_utf16(&title[..length as usize]).ok()
+}
+pub fn window_process_id(hwnd: Hwnd) -> Option<u32> {
+    println!("enter: window_process_id");
+    let mut pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    }
+    println!("exit: window_process_id");
+    match pid {
+        0 => None,
+        pid => Some(pid),
+    }
+}
+fn to_wide(text: impl AsRef<OsStr>) -> Vec<u16> {
+    text.as_ref().encode_wide().chain(std::iter::once(0)).collect()
+}
+pub fn create_private_file(path: &Path) -> std::io::Result<File> {
+    println!("enter: create_private_file");
+    // owner, SYSTEM and administrators only, without inheriting the parent directory's entries
+    let sddl = to_wide("D:P(A;;FA;;;OW)(A;;FA;;;SY)(A;;FA;;;BA)");
+    let path = to_wide(path.as_os_str());
+    let mut descriptor = PSECURITY_DESCRIPTOR::default();
+    let ret = unsafe {
+        if !ConvertStringSecurityDescriptorToSecurityDescriptorW(PCWSTR::from_raw(sddl.as_ptr()), SDDL_REVISION_1, &mut descriptor, None).as_bool() {
+            println!("exit: create_private_file");
+            return Err(std::io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor.0,
+            bInheritHandle: false.into(),
+        };
+        let handle = CreateFileW(PCWSTR::from_raw(path.as_ptr()), FILE_GENERIC_WRITE, FILE_SHARE_NONE, Some(&attributes), CREATE_NEW, FILE_ATTRIBUTE_NORMAL, HANDLE(0));
+        let _ = LocalFree(HLOCAL(descriptor.0 as isize));
+        handle.map(|handle| File::from_raw_handle(handle.0 as RawHandle))
+    };
+    println!("exit: create_private_file");
+    ret.map_err(std::io::Error::other)
+}
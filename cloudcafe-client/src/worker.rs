@@ -0,0 +1,201 @@
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    Never,
+    OnPanic { max_restarts: u32, backoff: Duration },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkerExit {
+    Stopped,
+    Finished,
+    Panicked(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let end = Instant::now() + duration;
+        while !self.is_cancelled() {
+            let now = Instant::now();
+            if now >= end {
+                return true;
+            }
+            thread::sleep(SLEEP_SLICE.min(end - now));
+        }
+        false
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(msg) => msg.to_string(),
+            Err(_) => String::from("unknown panic"),
+        },
+    }
+}
+
+pub fn supervise(name: &str, policy: RestartPolicy, cancel: &CancelToken, mut body: impl FnMut(&CancelToken)) -> WorkerExit {
+    let mut restarts = 0;
+    loop {
+        let result = catch_unwind(AssertUnwindSafe(|| body(cancel)));
+        if cancel.is_cancelled() {
+            return WorkerExit::Stopped;
+        }
+        let msg = match result {
+            Ok(()) => return WorkerExit::Finished,
+            Err(payload) => panic_message(payload),
+        };
+        match policy {
+            RestartPolicy::OnPanic { max_restarts, backoff } if restarts < max_restarts => {
+                restarts += 1;
+                println!("worker {name} panicked ({msg}), restart {restarts}/{max_restarts}");
+                if !cancel.sleep(backoff) {
+                    return WorkerExit::Stopped;
+                }
+            }
+            _ => return WorkerExit::Panicked(msg),
+        }
+    }
+}
+
+pub struct Worker {
+    name: String,
+    cancel: CancelToken,
+    thread: Option<JoinHandle<WorkerExit>>,
+}
+impl Worker {
+    pub fn spawn(name: &str, policy: RestartPolicy, body: impl FnMut(&CancelToken) + Send + 'static) -> Self {
+        let cancel = CancelToken::default();
+        let thread = {
+            let name = name.to_string();
+            let cancel = cancel.clone();
+            thread::Builder::new().name(name.clone()).spawn(move || supervise(&name, policy, &cancel, body)).expect("unable to spawn worker thread")
+        };
+        Self {
+            name: name.to_string(),
+            cancel,
+            thread: Some(thread),
+        }
+    }
+    pub fn stop(&mut self) -> Option<WorkerExit> {
+        self.cancel.cancel();
+        let exit = match self.thread.take()?.join() {
+            Ok(exit) => exit,
+            Err(payload) => WorkerExit::Panicked(panic_message(payload)),
+        };
+        println!("worker {} exited: {:?}", self.name, exit);
+        Some(exit)
+    }
+}
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+    use super::*;
+
+    const RESTART: RestartPolicy = RestartPolicy::OnPanic { max_restarts: 2, backoff: Duration::ZERO };
+
+    #[test]
+    fn restarts_until_the_limit_then_reports_the_panic() {
+        let mut runs = 0;
+        let exit = supervise("test", RESTART, &CancelToken::default(), |_| {
+            runs += 1;
+            panic!("run {runs}");
+        });
+        assert_eq!(exit, WorkerExit::Panicked(String::from("run 3")));
+        assert_eq!(runs, 3);
+        let mut runs = 0;
+        let exit = supervise("test", RESTART, &CancelToken::default(), |_| {
+            runs += 1;
+            if runs < 3 {
+                panic!("once more");
+            }
+        });
+        assert_eq!((exit, runs), (WorkerExit::Finished, 3));
+    }
+
+    #[test]
+    fn never_stops_at_the_first_panic() {
+        let mut runs = 0;
+        let exit = supervise("test", RestartPolicy::Never, &CancelToken::default(), |_| {
+            runs += 1;
+            panic!("static message");
+        });
+        assert_eq!((exit, runs), (WorkerExit::Panicked(String::from("static message")), 1));
+    }
+
+    #[test]
+    fn cancel_during_backoff_stops() {
+        let cancel = CancelToken::default();
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                cancel.cancel();
+            })
+        };
+        let start = Instant::now();
+        let policy = RestartPolicy::OnPanic { max_restarts: 1, backoff: Duration::from_secs(60) };
+        let mut runs = 0;
+        let exit = supervise("test", policy, &cancel, |_| {
+            runs += 1;
+            panic!("backoff");
+        });
+        canceller.join().unwrap();
+        assert_eq!((exit, runs), (WorkerExit::Stopped, 1));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn drop_cancels_and_joins() {
+        let ticks = Arc::new(AtomicU32::new(0));
+        let exited = Arc::new(AtomicBool::new(false));
+        let worker = {
+            let ticks = ticks.clone();
+            let exited = exited.clone();
+            Worker::spawn("test", RestartPolicy::Never, move |cancel| {
+                while cancel.sleep(Duration::from_millis(5)) {
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                }
+                thread::sleep(Duration::from_millis(20));
+                exited.store(true, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(30));
+        drop(worker);
+        assert!(exited.load(Ordering::SeqCst));
+        assert!(ticks.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn stop_reports_how_the_worker_ended() {
+        let mut worker = Worker::spawn("test", RestartPolicy::Never, |_| panic!("boom"));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(worker.stop(), Some(WorkerExit::Panicked(String::from("boom"))));
+        assert_eq!(worker.stop(), None);
+    }
+}
@@ -0,0 +1,20 @@
+use uuid::Uuid;
+use cloudcafe_common::remote::{Frame, RemoteInput, RemoteWindow};
+
+pub trait SessionHost {
+    fn windows(&mut self) -> Vec<RemoteWindow>;
+    fn next_frame(&mut self, window_id: u64) -> Option<Frame>;
+    fn input(&mut self, session_id: Uuid, input: RemoteInput);
+}
+
+pub struct NullHost;
+
+impl SessionHost for NullHost {
+    fn windows(&mut self) -> Vec<RemoteWindow> {
+        vec![]
+    }
+    fn next_frame(&mut self, _window_id: u64) -> Option<Frame> {
+        None
+    }
+    fn input(&mut self, _session_id: Uuid, _input: RemoteInput) {}
+}
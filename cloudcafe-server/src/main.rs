@@ -1,3 +1,27 @@
-fn main() {
-    println!("Hello, world!");
+mod session;
+mod host;
+mod server;
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::thread;
+use std::time::{Duration, Instant};
+use color_eyre::Result;
+use crate::host::NullHost;
+use crate::server::Server;
+
+const DEFAULT_PORT: u16 = 25556;
+const TICK: Duration = Duration::from_millis(1);
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let addr = match std::env::args().nth(1) {
+        Some(addr) => addr.parse::<SocketAddr>()?,
+        None => SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_PORT)),
+    };
+    let mut server = Server::bind(addr, NullHost)?;
+    println!("cloudcafe server listening on {}", server.local_addr()?);
+    loop {
+        server.poll(Instant::now())?;
+        thread::sleep(TICK);
+    }
 }
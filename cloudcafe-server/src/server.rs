@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+use color_eyre::Result;
+use laminar::{Packet, Socket, SocketEvent};
+use cloudcafe_common::codec;
+use cloudcafe_common::codec::CodecError;
+use cloudcafe_common::remote::{Channel, ClientMsg, ServerMsg};
+use crate::host::SessionHost;
+use crate::session::{Session, SessionState};
+
+const MAX_SESSIONS: usize = 64;
+
+pub struct Server<H: SessionHost> {
+    socket: Socket,
+    sessions: HashMap<SocketAddr, Session>,
+    host: H,
+    frame_counter: u32,
+}
+
+fn to_packet(addr: SocketAddr, channel: Channel, payload: Vec<u8>) -> Packet {
+    match channel {
+        Channel::Frame => Packet::unreliable(addr, payload),
+        _ => Packet::reliable_ordered(addr, payload, Some(channel.stream_id())),
+    }
+}
+
+impl<H: SessionHost> Server<H> {
+    pub fn bind(addr: SocketAddr, host: H) -> Result<Self> {
+        let socket = Socket::bind(addr)?;
+        Ok(Self::from_socket(socket, host))
+    }
+    pub fn from_socket(socket: Socket, host: H) -> Self {
+        Self {
+            socket,
+            sessions: HashMap::new(),
+            host,
+            frame_counter: 0,
+        }
+    }
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+    pub fn session_count(&self) -> usize {
+        self.sessions.values().filter(|session| session.session_id().is_some()).count()
+    }
+    pub fn poll(&mut self, now: Instant) -> Result<()> {
+        self.socket.manual_poll(now);
+        while let Some(event) = self.socket.recv() {
+            self.handle_event(event)?;
+        }
+        self.publish()?;
+        self.socket.manual_poll(now);
+        Ok(())
+    }
+    fn handle_event(&mut self, event: SocketEvent) -> Result<()> {
+        match event {
+            SocketEvent::Packet(packet) => {
+                let addr = packet.addr();
+                let msg: ClientMsg = match codec::decode(packet.payload()) {
+                    Ok(msg) => msg,
+                    Err(CodecError::VersionMismatch { ours, theirs }) => {
+                        println!("rejecting {addr}, it speaks protocol {theirs}");
+                        let reason = format!("client speaks protocol {theirs}, server speaks {ours}");
+                        return self.send(addr, &ServerMsg::Rejected { reason });
+                    }
+                    Err(err) => {
+                        println!("dropping undecodable packet from {addr}: {err}");
+                        return Ok(());
+                    }
+                };
+                self.handle_msg(addr, msg)?;
+            }
+            SocketEvent::Connect(addr) => {
+                println!("connection from {addr}");
+            }
+            SocketEvent::Timeout(addr) | SocketEvent::Disconnect(addr) => {
+                if let Some(session) = self.sessions.remove(&addr) {
+                    println!("session with {} closed", session.addr);
+                }
+            }
+        }
+        Ok(())
+    }
+    fn handle_msg(&mut self, addr: SocketAddr, msg: ClientMsg) -> Result<()> {
+        match msg {
+            ClientMsg::Hello { protocol_version, client_name } => {
+                if !self.sessions.contains_key(&addr) && self.sessions.len() >= MAX_SESSIONS {
+                    println!("rejecting {addr}, already serving {MAX_SESSIONS} sessions");
+                    let reason = format!("server is full ({MAX_SESSIONS} sessions)");
+                    return self.send(addr, &ServerMsg::Rejected { reason });
+                }
+                let session = self.sessions.entry(addr).or_insert_with(|| Session::new(addr));
+                let reply = session.handle_hello(protocol_version, &client_name);
+                if session.state == SessionState::Closed {
+                    self.sessions.remove(&addr);
+                }
+                self.send(addr, &reply)?;
+            }
+            ClientMsg::Input(input) => {
+                match self.sessions.get(&addr).and_then(Session::session_id) {
+                    Some(session_id) => self.host.input(session_id, input),
+                    None => println!("input from {addr} before handshake, ignoring"),
+                }
+            }
+            ClientMsg::Goodbye => {
+                if self.sessions.remove(&addr).is_none() {
+                    println!("goodbye from {addr} without a session, ignoring");
+                }
+            }
+        }
+        Ok(())
+    }
+    fn publish(&mut self) -> Result<()> {
+        if self.session_count() == 0 {
+            return Ok(());
+        }
+        let windows = self.host.windows();
+        let mut outgoing = Vec::new();
+        for session in self.sessions.values_mut() {
+            if let Some(msg) = session.window_list_update(&windows) {
+                outgoing.push((session.addr, msg));
+            }
+        }
+        for window in &windows {
+            if let Some(frame) = self.host.next_frame(window.id) {
+                self.frame_counter = self.frame_counter.wrapping_add(1);
+                for chunk in frame.into_chunks(window.id, self.frame_counter) {
+                    for session in self.sessions.values() {
+                        if session.session_id().is_some() {
+                            outgoing.push((session.addr, ServerMsg::FrameChunk(chunk.clone())));
+                        }
+                    }
+                }
+            }
+        }
+        for (addr, msg) in outgoing {
+            self.send(addr, &msg)?;
+        }
+        Ok(())
+    }
+    fn send(&mut self, addr: SocketAddr, msg: &ServerMsg) -> Result<()> {
+        let payload = codec::encode(msg)?;
+        self.socket.send(to_packet(addr, msg.channel(), payload))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use uuid::Uuid;
+    use cloudcafe_common::codec::PROTOCOL_VERSION;
+    use cloudcafe_common::remote::{Frame, RemoteInput, RemoteWindow};
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingHost {
+        inputs: Vec<(Uuid, RemoteInput)>,
+    }
+    impl SessionHost for RecordingHost {
+        fn windows(&mut self) -> Vec<RemoteWindow> {
+            vec![RemoteWindow { id: 1, title: String::from("notepad"), width: 640, height: 480 }]
+        }
+        fn next_frame(&mut self, _window_id: u64) -> Option<Frame> {
+            None
+        }
+        fn input(&mut self, session_id: Uuid, input: RemoteInput) {
+            self.inputs.push((session_id, input));
+        }
+    }
+
+    struct Loopback {
+        server: Server<RecordingHost>,
+        client: Socket,
+        server_addr: SocketAddr,
+    }
+    impl Loopback {
+        fn new() -> Self {
+            let server = Server::bind("127.0.0.1:0".parse().unwrap(), RecordingHost::default()).unwrap();
+            let server_addr = server.local_addr().unwrap();
+            let client = Socket::bind("127.0.0.1:0").unwrap();
+            Self { server, client, server_addr }
+        }
+        fn send(&mut self, msg: &ClientMsg) {
+            let payload = codec::encode(msg).unwrap();
+            self.client.send(Packet::reliable_ordered(self.server_addr, payload, Some(msg.channel().stream_id()))).unwrap();
+        }
+        fn pump_until<T>(&mut self, mut done: impl FnMut(&mut Self, Vec<ServerMsg>) -> Option<T>) -> T {
+            for _ in 0..500 {
+                let now = Instant::now();
+                self.client.manual_poll(now);
+                self.server.poll(now).unwrap();
+                self.client.manual_poll(now);
+                let mut received = Vec::new();
+                while let Some(event) = self.client.recv() {
+                    if let SocketEvent::Packet(packet) = event {
+                        received.push(codec::decode::<ServerMsg>(packet.payload()).unwrap());
+                    }
+                }
+                if let Some(result) = done(self, received) {
+                    return result;
+                }
+                thread::sleep(Duration::from_millis(2));
+            }
+            panic!("loopback did not settle");
+        }
+    }
+
+    #[test]
+    fn handshake_window_list_and_input_over_loopback() {
+        let mut loopback = Loopback::new();
+        loopback.send(&ClientMsg::Hello { protocol_version: PROTOCOL_VERSION, client_name: String::from("test") });
+        let mut session_id = None;
+        let mut window_list = None;
+        loopback.pump_until(|_, received| {
+            for msg in received {
+                match msg {
+                    ServerMsg::Welcome { session_id: id, protocol_version } => {
+                        assert_eq!(protocol_version, PROTOCOL_VERSION);
+                        session_id = Some(id);
+                    }
+                    ServerMsg::WindowList(windows) => window_list = Some(windows),
+                    msg => panic!("unexpected {msg:?}"),
+                }
+            }
+            (session_id.is_some() && window_list.is_some()).then_some(())
+        });
+        assert_eq!(loopback.server.session_count(), 1);
+        assert_eq!(window_list.unwrap()[0].title, "notepad");
+
+        let input = RemoteInput::Key { code: 65, pressed: true };
+        loopback.send(&ClientMsg::Input(input));
+        let inputs = loopback.pump_until(|loopback, _| (!loopback.server.host.inputs.is_empty()).then(|| loopback.server.host.inputs.clone()));
+        assert_eq!(inputs, vec![(session_id.unwrap(), input)]);
+
+        loopback.send(&ClientMsg::Goodbye);
+        loopback.pump_until(|loopback, _| (loopback.server.session_count() == 0).then_some(()));
+    }
+
+    #[test]
+    fn rejects_hello_with_other_protocol_version() {
+        let mut loopback = Loopback::new();
+        loopback.send(&ClientMsg::Hello { protocol_version: PROTOCOL_VERSION + 1, client_name: String::from("test") });
+        let reason = loopback.pump_until(|_, received| received.into_iter().find_map(|msg| match msg {
+            ServerMsg::Rejected { reason } => Some(reason),
+            _ => None,
+        }));
+        assert!(reason.contains(&(PROTOCOL_VERSION + 1).to_string()));
+        assert_eq!(loopback.server.session_count(), 0);
+    }
+
+    #[test]
+    fn rejects_frames_with_other_codec_version() {
+        let mut loopback = Loopback::new();
+        let mut payload = codec::encode(&ClientMsg::Goodbye).unwrap();
+        payload[4..6].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+        loopback.client.send(Packet::reliable_ordered(loopback.server_addr, payload, Some(ClientMsg::Goodbye.channel().stream_id()))).unwrap();
+        let reason = loopback.pump_until(|_, received| received.into_iter().find_map(|msg| match msg {
+            ServerMsg::Rejected { reason } => Some(reason),
+            _ => None,
+        }));
+        assert!(reason.contains(&(PROTOCOL_VERSION + 1).to_string()));
+        assert!(loopback.server.sessions.is_empty());
+    }
+
+    #[test]
+    fn ignores_peers_without_a_handshake() {
+        let mut loopback = Loopback::new();
+        let addr = loopback.client.local_addr().unwrap();
+        loopback.server.handle_msg(addr, ClientMsg::Input(RemoteInput::Key { code: 65, pressed: true })).unwrap();
+        loopback.server.handle_msg(addr, ClientMsg::Goodbye).unwrap();
+        assert!(loopback.server.sessions.is_empty());
+        assert!(loopback.server.host.inputs.is_empty());
+    }
+
+    #[test]
+    fn rejects_hello_when_full() {
+        let mut loopback = Loopback::new();
+        for port in 0..MAX_SESSIONS as u16 {
+            let addr = SocketAddr::from(([127, 0, 0, 2], 1000 + port));
+            loopback.server.sessions.insert(addr, Session::new(addr));
+        }
+        loopback.send(&ClientMsg::Hello { protocol_version: PROTOCOL_VERSION, client_name: String::from("test") });
+        let reason = loopback.pump_until(|_, received| received.into_iter().find_map(|msg| match msg {
+            ServerMsg::Rejected { reason } => Some(reason),
+            _ => None,
+        }));
+        assert!(reason.contains("full"));
+        assert_eq!(loopback.server.sessions.len(), MAX_SESSIONS);
+    }
+}
@@ -0,0 +1,60 @@
+use std::net::SocketAddr;
+use uuid::Uuid;
+use cloudcafe_common::codec::PROTOCOL_VERSION;
+use cloudcafe_common::remote::{RemoteWindow, ServerMsg};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SessionState {
+    AwaitingHello,
+    Established {
+        session_id: Uuid,
+    },
+    Closed,
+}
+
+pub struct Session {
+    pub addr: SocketAddr,
+    pub state: SessionState,
+    sent_window_list: Option<Vec<RemoteWindow>>,
+}
+impl Session {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            state: SessionState::AwaitingHello,
+            sent_window_list: None,
+        }
+    }
+    pub fn session_id(&self) -> Option<Uuid> {
+        match self.state {
+            SessionState::Established { session_id } => Some(session_id),
+            _ => None,
+        }
+    }
+    pub fn handle_hello(&mut self, protocol_version: u16, client_name: &str) -> ServerMsg {
+        if protocol_version != PROTOCOL_VERSION {
+            self.state = SessionState::Closed;
+            return ServerMsg::Rejected {
+                reason: format!("client speaks protocol {protocol_version}, server speaks {PROTOCOL_VERSION}"),
+            };
+        }
+        let session_id = match self.state {
+            SessionState::Established { session_id } => session_id,
+            _ => Uuid::new_v4(),
+        };
+        println!("session {session_id} established with {client_name} at {}", self.addr);
+        self.state = SessionState::Established { session_id };
+        ServerMsg::Welcome {
+            session_id,
+            protocol_version: PROTOCOL_VERSION,
+        }
+    }
+    pub fn window_list_update(&mut self, windows: &[RemoteWindow]) -> Option<ServerMsg> {
+        self.session_id()?;
+        if self.sent_window_list.as_deref() == Some(windows) {
+            return None;
+        }
+        self.sent_window_list.replace(windows.to_vec());
+        Some(ServerMsg::WindowList(windows.to_vec()))
+    }
+}
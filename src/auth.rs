@@ -0,0 +1,111 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+
+pub const SECRET_LEN: usize = 32;
+pub const NONCE_LEN: usize = 32;
+pub const MAC_LEN: usize = 32;
+const AUTH_CONTEXT: &[u8] = b"cloudcafe-service-auth";
+
+pub type Nonce = [u8; NONCE_LEN];
+pub type AuthMac = [u8; MAC_LEN];
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SharedSecret([u8; SECRET_LEN]);
+
+impl SharedSecret {
+    pub fn generate() -> Self {
+        let mut secret = [0; SECRET_LEN];
+        OsRng.fill_bytes(&mut secret);
+        Self(secret)
+    }
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        // from_str_radix alone would let a leading '+' through
+        if hex.len() != SECRET_LEN * 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let mut secret = [0; SECRET_LEN];
+        for (i, byte) in secret.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(Self(secret))
+    }
+    fn mac(&self, nonce: &Nonce) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("hmac accepts keys of any length");
+        mac.update(AUTH_CONTEXT);
+        mac.update(nonce);
+        mac
+    }
+    pub fn respond(&self, nonce: &Nonce) -> AuthMac {
+        self.mac(nonce).finalize().into_bytes().into()
+    }
+    pub fn verify(&self, nonce: &Nonce, response: &AuthMac) -> bool {
+        self.mac(nonce).verify_slice(response).is_ok()
+    }
+}
+
+impl std::fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedSecret(..)")
+    }
+}
+
+pub fn generate_nonce() -> Nonce {
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: Nonce = [7; NONCE_LEN];
+
+    #[test]
+    fn verify_accepts_the_matching_response() {
+        let secret = SharedSecret::generate();
+        assert!(secret.verify(&NONCE, &secret.respond(&NONCE)));
+    }
+
+    #[test]
+    fn verify_rejects_wrong_responses() {
+        let secret = SharedSecret::generate();
+        let response = secret.respond(&NONCE);
+        let mut other_nonce = NONCE;
+        other_nonce[0] ^= 1;
+        assert!(!secret.verify(&other_nonce, &response));
+        assert!(!SharedSecret::generate().verify(&NONCE, &response));
+        for index in [0, MAC_LEN - 1] {
+            let mut tampered = response;
+            tampered[index] ^= 0x80;
+            assert!(!secret.verify(&NONCE, &tampered));
+        }
+        assert!(!secret.verify(&NONCE, &[0; MAC_LEN]));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let secret = SharedSecret::generate();
+        let hex = secret.to_hex();
+        assert_eq!(hex.len(), SECRET_LEN * 2);
+        assert_eq!(SharedSecret::from_hex(&hex), Some(secret.clone()));
+        assert_eq!(SharedSecret::from_hex(&hex.to_ascii_uppercase()), Some(secret));
+    }
+
+    #[test]
+    fn from_hex_rejects_bad_input() {
+        let hex = "ab".repeat(SECRET_LEN);
+        assert!(SharedSecret::from_hex(&hex).is_some());
+        assert!(SharedSecret::from_hex(&hex[2..]).is_none());
+        assert!(SharedSecret::from_hex(&format!("{hex}ab")).is_none());
+        assert!(SharedSecret::from_hex("").is_none());
+        assert!(SharedSecret::from_hex(&format!("zz{}", &hex[2..])).is_none());
+        assert!(SharedSecret::from_hex(&format!("+a{}", &hex[2..])).is_none());
+        assert!(SharedSecret::from_hex(&format!("é{}", &hex[2..])).is_none());
+    }
+}
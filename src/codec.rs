@@ -0,0 +1,305 @@
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const PROTOCOL_VERSION: u16 = 5;
+pub const HEADER_LEN: usize = 7;
+pub const MAX_PAYLOAD_LEN: u32 = 16 * 1024 * 1024;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    ClientToService = 1,
+    ServiceToClient = 2,
+    RemoteClient = 3,
+    RemoteServer = 4,
+    ServiceHello = 5,
+    ServiceHelloReply = 6,
+}
+impl MessageKind {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => MessageKind::ClientToService,
+            2 => MessageKind::ServiceToClient,
+            3 => MessageKind::RemoteClient,
+            4 => MessageKind::RemoteServer,
+            5 => MessageKind::ServiceHello,
+            6 => MessageKind::ServiceHelloReply,
+            _ => return None,
+        })
+    }
+}
+
+pub trait WireMessage: Serialize + DeserializeOwned {
+    const KIND: MessageKind;
+    const VERSIONED: bool = true;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub length: u32,
+    pub version: u16,
+    pub kind: u8,
+}
+impl FrameHeader {
+    pub fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&self.length.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.kind;
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodecError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(CodecError::Truncated);
+        }
+        let length = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if length > MAX_PAYLOAD_LEN {
+            return Err(CodecError::TooLarge(length));
+        }
+        Ok(Self {
+            length,
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            kind: bytes[6],
+        })
+    }
+    fn check<M: WireMessage>(&self) -> Result<(), CodecError> {
+        // handshake messages are read before a version is agreed on, so only the frames after it must match exactly
+        if M::VERSIONED && self.version != PROTOCOL_VERSION {
+            return Err(CodecError::VersionMismatch { ours: PROTOCOL_VERSION, theirs: self.version });
+        }
+        if self.kind != M::KIND as u8 {
+            return Err(CodecError::UnexpectedKind { expected: M::KIND, found: self.kind });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(std::io::Error),
+    Bincode(bincode::Error),
+    Truncated,
+    TooLarge(u32),
+    VersionMismatch {
+        ours: u16,
+        theirs: u16,
+    },
+    UnexpectedKind {
+        expected: MessageKind,
+        found: u8,
+    },
+}
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Io(err) => write!(f, "io error: {err}"),
+            CodecError::Bincode(err) => write!(f, "unable to decode message: {err}"),
+            CodecError::Truncated => write!(f, "frame was truncated"),
+            CodecError::TooLarge(length) => write!(f, "frame payload of {length} bytes is larger than {MAX_PAYLOAD_LEN}"),
+            CodecError::VersionMismatch { ours, theirs } => write!(f, "peer speaks protocol version {theirs}, we speak {ours}"),
+            CodecError::UnexpectedKind { expected, found } => write!(f, "expected a {expected:?} message, got kind {found}"),
+        }
+    }
+}
+impl std::error::Error for CodecError {}
+impl From<std::io::Error> for CodecError {
+    fn from(err: std::io::Error) -> Self {
+        CodecError::Io(err)
+    }
+}
+impl From<bincode::Error> for CodecError {
+    fn from(err: bincode::Error) -> Self {
+        CodecError::Bincode(err)
+    }
+}
+
+pub fn encode<M: WireMessage>(msg: &M) -> Result<Vec<u8>, CodecError> {
+    let payload = bincode::serialize(msg)?;
+    let length = payload.len() as u32;
+    if length > MAX_PAYLOAD_LEN {
+        return Err(CodecError::TooLarge(length));
+    }
+    let header = FrameHeader {
+        length,
+        version: PROTOCOL_VERSION,
+        kind: M::KIND as u8,
+    };
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(&header.to_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+pub fn decode<M: WireMessage>(bytes: &[u8]) -> Result<M, CodecError> {
+    let header = FrameHeader::from_bytes(bytes)?;
+    header.check::<M>()?;
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() < header.length as usize {
+        return Err(CodecError::Truncated);
+    }
+    Ok(bincode::deserialize(&payload[..header.length as usize])?)
+}
+
+pub fn write_message<M: WireMessage>(mut writer: impl Write, msg: &M) -> Result<(), CodecError> {
+    writer.write_all(&encode(msg)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_header(mut reader: impl Read) -> Result<FrameHeader, CodecError> {
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header)?;
+    FrameHeader::from_bytes(&header)
+}
+
+fn read_payload(mut reader: impl Read, header: &FrameHeader) -> Result<Vec<u8>, CodecError> {
+    let mut payload = vec![0; header.length as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+pub fn read_frame(mut reader: impl Read) -> Result<(FrameHeader, Vec<u8>), CodecError> {
+    let header = read_header(&mut reader)?;
+    let payload = read_payload(reader, &header)?;
+    Ok((header, payload))
+}
+
+pub fn read_message<M: WireMessage>(mut reader: impl Read) -> Result<M, CodecError> {
+    let header = read_header(&mut reader)?;
+    header.check::<M>()?;
+    let payload = read_payload(reader, &header)?;
+    Ok(bincode::deserialize(&payload)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+    use std::io::Cursor;
+    use uuid::Uuid;
+    use crate::remote::{ClientMsg, FrameChunk, RemoteInput, RemoteWindow, ServerMsg};
+    use crate::service::{ClientToServiceMsg, DriverState, ServiceHello, ServiceHelloReply, ServiceToClientMsg};
+    use super::*;
+
+    fn round_trip<M: WireMessage + PartialEq + Debug>(msg: M) {
+        let bytes = encode(&msg).unwrap();
+        assert_eq!(FrameHeader::from_bytes(&bytes).unwrap().kind, M::KIND as u8);
+        assert_eq!(decode::<M>(&bytes).unwrap(), msg);
+        assert_eq!(read_message::<M>(Cursor::new(&bytes)).unwrap(), msg);
+    }
+    fn with_version(mut bytes: Vec<u8>, version: u16) -> Vec<u8> {
+        bytes[4..6].copy_from_slice(&version.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn client_to_service_round_trip() {
+        for msg in [
+            ClientToServiceMsg::Authenticate { mac: [7; 32] },
+            ClientToServiceMsg::ProcessId { client_id: 1, window_id: 2 },
+            ClientToServiceMsg::EnableDriver,
+            ClientToServiceMsg::DisableDriver,
+            ClientToServiceMsg::QueryDriverStatus,
+            ClientToServiceMsg::SetResolution { width: 1920, height: 1080 },
+            ClientToServiceMsg::Heartbeat,
+            ClientToServiceMsg::Shutdown,
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn service_to_client_round_trip() {
+        for msg in [
+            ServiceToClientMsg::Challenge { nonce: [3; 32] },
+            ServiceToClientMsg::AuthResult { accepted: true },
+            ServiceToClientMsg::DisplaySetupComplete(-42),
+            ServiceToClientMsg::DriverStatus { state: DriverState::Enabled, sessions: 2 },
+            ServiceToClientMsg::Ack,
+            ServiceToClientMsg::HeartbeatAck,
+            ServiceToClientMsg::Failed(String::from("no driver")),
+            ServiceToClientMsg::ShuttingDown,
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn service_handshake_round_trip() {
+        round_trip(ServiceHello::ours());
+        round_trip(ServiceHello { driver_name: Some(String::from("IddSampleDriver Device")), ..ServiceHello::ours() });
+        round_trip(ServiceHelloReply::Welcome { protocol_version: PROTOCOL_VERSION, capabilities: 5, build: String::from("0.1.0") });
+        round_trip(ServiceHelloReply::Incompatible { protocol_version: 9, min_protocol_version: 8, build: String::from("0.9.0"), reason: String::from("too new") });
+    }
+
+    #[test]
+    fn remote_round_trip() {
+        for msg in [
+            ClientMsg::Hello { protocol_version: PROTOCOL_VERSION, client_name: String::from("headset") },
+            ClientMsg::Input(RemoteInput::MouseMove { window_id: 1, x: -5, y: 10 }),
+            ClientMsg::Input(RemoteInput::MouseButton { window_id: 1, button: 2, pressed: true }),
+            ClientMsg::Input(RemoteInput::Wheel { window_id: 1, delta: -120 }),
+            ClientMsg::Input(RemoteInput::Key { code: 65, pressed: false }),
+            ClientMsg::Goodbye,
+        ] {
+            round_trip(msg);
+        }
+        for msg in [
+            ServerMsg::Welcome { session_id: Uuid::from_u128(1), protocol_version: PROTOCOL_VERSION },
+            ServerMsg::Rejected { reason: String::from("busy") },
+            ServerMsg::WindowList(vec![RemoteWindow { id: 4, title: String::from("notepad"), width: 640, height: 480 }]),
+            ServerMsg::FrameChunk(FrameChunk { window_id: 4, frame_id: 9, chunk_index: 0, chunk_count: 1, width: 2, height: 1, data: vec![1, 2, 3, 4, 5, 6, 7, 8] }),
+        ] {
+            round_trip(msg);
+        }
+    }
+
+    #[test]
+    fn truncated_header() {
+        let bytes = encode(&ClientToServiceMsg::Heartbeat).unwrap();
+        assert!(matches!(decode::<ClientToServiceMsg>(&bytes[..HEADER_LEN - 1]), Err(CodecError::Truncated)));
+        match read_message::<ClientToServiceMsg>(Cursor::new(&bytes[..HEADER_LEN - 1])) {
+            Err(CodecError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+            other => panic!("expected eof, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let bytes = encode(&ServiceToClientMsg::Failed(String::from("no driver"))).unwrap();
+        assert!(matches!(decode::<ServiceToClientMsg>(&bytes[..bytes.len() - 1]), Err(CodecError::Truncated)));
+        assert!(matches!(read_message::<ServiceToClientMsg>(Cursor::new(&bytes[..bytes.len() - 1])), Err(CodecError::Io(_))));
+    }
+
+    #[test]
+    fn oversized_length() {
+        let header = FrameHeader { length: MAX_PAYLOAD_LEN + 1, version: PROTOCOL_VERSION, kind: MessageKind::ClientToService as u8 };
+        assert!(matches!(decode::<ClientToServiceMsg>(&header.to_bytes()), Err(CodecError::TooLarge(_))));
+        assert!(matches!(read_message::<ClientToServiceMsg>(Cursor::new(header.to_bytes())), Err(CodecError::TooLarge(_))));
+    }
+
+    #[test]
+    fn wrong_version() {
+        let bytes = with_version(encode(&ClientToServiceMsg::Heartbeat).unwrap(), PROTOCOL_VERSION + 1);
+        let expected = |result: Result<ClientToServiceMsg, CodecError>| matches!(result, Err(CodecError::VersionMismatch { ours: PROTOCOL_VERSION, theirs }) if theirs == PROTOCOL_VERSION + 1);
+        assert!(expected(decode(&bytes)));
+        assert!(expected(read_message(Cursor::new(&bytes))));
+    }
+
+    #[test]
+    fn wrong_kind() {
+        let bytes = encode(&ClientMsg::Goodbye).unwrap();
+        let expected = |result: Result<ServerMsg, CodecError>| matches!(result, Err(CodecError::UnexpectedKind { expected: MessageKind::RemoteServer, found: 3 }));
+        assert!(expected(decode(&bytes)));
+        assert!(expected(read_message(Cursor::new(&bytes))));
+    }
+
+    #[test]
+    fn read_message_checks_header_before_payload() {
+        let header = FrameHeader { length: MAX_PAYLOAD_LEN, version: PROTOCOL_VERSION, kind: MessageKind::RemoteClient as u8 };
+        assert!(matches!(read_message::<ClientToServiceMsg>(Cursor::new(header.to_bytes())), Err(CodecError::UnexpectedKind { .. })));
+        let header = FrameHeader { version: PROTOCOL_VERSION + 1, kind: MessageKind::ClientToService as u8, ..header };
+        assert!(matches!(read_message::<ClientToServiceMsg>(Cursor::new(header.to_bytes())), Err(CodecError::VersionMismatch { .. })));
+    }
+}
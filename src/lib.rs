@@ -0,0 +1,4 @@
+pub mod codec;
+pub mod service;
+pub mod remote;
+pub mod auth;
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::codec::{MessageKind, WireMessage};
+
+pub const FRAME_CHUNK_SIZE: usize = 1024;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    Control,
+    WindowList,
+    Frame,
+    Input,
+}
+impl Channel {
+    pub fn stream_id(self) -> u8 {
+        match self {
+            Channel::Control => 0,
+            Channel::WindowList => 1,
+            Channel::Frame => 2,
+            Channel::Input => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClientMsg {
+    Hello {
+        protocol_version: u16,
+        client_name: String,
+    },
+    Input(RemoteInput),
+    Goodbye,
+}
+impl WireMessage for ClientMsg {
+    const KIND: MessageKind = MessageKind::RemoteClient;
+}
+impl ClientMsg {
+    pub fn channel(&self) -> Channel {
+        match self {
+            ClientMsg::Hello { .. } | ClientMsg::Goodbye => Channel::Control,
+            ClientMsg::Input(_) => Channel::Input,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ServerMsg {
+    Welcome {
+        session_id: Uuid,
+        protocol_version: u16,
+    },
+    Rejected {
+        reason: String,
+    },
+    WindowList(Vec<RemoteWindow>),
+    FrameChunk(FrameChunk),
+}
+impl WireMessage for ServerMsg {
+    const KIND: MessageKind = MessageKind::RemoteServer;
+}
+impl ServerMsg {
+    pub fn channel(&self) -> Channel {
+        match self {
+            ServerMsg::Welcome { .. } | ServerMsg::Rejected { .. } => Channel::Control,
+            ServerMsg::WindowList(_) => Channel::WindowList,
+            ServerMsg::FrameChunk(_) => Channel::Frame,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteWindow {
+    pub id: u64,
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameChunk {
+    pub window_id: u64,
+    pub frame_id: u32,
+    pub chunk_index: u16,
+    pub chunk_count: u16,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RemoteInput {
+    MouseMove {
+        window_id: u64,
+        x: i32,
+        y: i32,
+    },
+    MouseButton {
+        window_id: u64,
+        button: u8,
+        pressed: bool,
+    },
+    Wheel {
+        window_id: u64,
+        delta: i32,
+    },
+    Key {
+        code: u32,
+        pressed: bool,
+    },
+}
+
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+impl Frame {
+    pub fn into_chunks(self, window_id: u64, frame_id: u32) -> Vec<FrameChunk> {
+        let chunks = self.data.chunks(FRAME_CHUNK_SIZE).collect::<Vec<_>>();
+        let chunk_count = chunks.len().max(1) as u16;
+        if chunks.is_empty() {
+            return vec![FrameChunk {
+                window_id,
+                frame_id,
+                chunk_index: 0,
+                chunk_count,
+                width: self.width,
+                height: self.height,
+                data: vec![],
+            }];
+        }
+        chunks.into_iter().enumerate().map(|(chunk_index, data)| FrameChunk {
+            window_id,
+            frame_id,
+            chunk_index: chunk_index as u16,
+            chunk_count,
+            width: self.width,
+            height: self.height,
+            data: data.to_vec(),
+        }).collect()
+    }
+}
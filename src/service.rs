@@ -0,0 +1,231 @@
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+use crate::auth::{AuthMac, Nonce};
+use crate::codec::{MessageKind, PROTOCOL_VERSION, WireMessage};
+
+// every frame after the handshake is checked against PROTOCOL_VERSION exactly, so no older protocol is compatible yet
+pub const MIN_SERVICE_PROTOCOL_VERSION: u16 = PROTOCOL_VERSION;
+
+bitflags! {
+    pub struct ServiceCapabilities: u32 {
+        const DISPLAY_DRIVER = 1 << 0;
+        const PROCESS_WATCH = 1 << 1;
+        const AUTHENTICATED = 1 << 2;
+        const COMMAND_LOOP = 1 << 3;
+        const SET_RESOLUTION = 1 << 4;
+        const HEARTBEAT = 1 << 5;
+    }
+}
+impl ServiceCapabilities {
+    pub fn supported() -> Self {
+        ServiceCapabilities::DISPLAY_DRIVER | ServiceCapabilities::PROCESS_WATCH | ServiceCapabilities::AUTHENTICATED
+            | ServiceCapabilities::COMMAND_LOOP | ServiceCapabilities::SET_RESOLUTION | ServiceCapabilities::HEARTBEAT
+    }
+    pub fn required() -> Self {
+        ServiceCapabilities::DISPLAY_DRIVER | ServiceCapabilities::AUTHENTICATED
+    }
+}
+
+// the hello and its reply are never version checked by the codec, so their layout must stay frozen
+// new fields only go at the end, older peers decode the leading fields and ignore the rest
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceHello {
+    pub protocol_version: u16,
+    pub min_protocol_version: u16,
+    pub capabilities: u32,
+    pub build: String,
+    pub driver_name: Option<String>,
+}
+impl ServiceHello {
+    pub fn ours() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_SERVICE_PROTOCOL_VERSION,
+            capabilities: ServiceCapabilities::supported().bits(),
+            build: env!("CARGO_PKG_VERSION").to_string(),
+            driver_name: None,
+        }
+    }
+}
+impl WireMessage for ServiceHello {
+    const KIND: MessageKind = MessageKind::ServiceHello;
+    const VERSIONED: bool = false;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServiceHelloReply {
+    Welcome {
+        protocol_version: u16,
+        capabilities: u32,
+        build: String,
+    },
+    Incompatible {
+        protocol_version: u16,
+        min_protocol_version: u16,
+        build: String,
+        reason: String,
+    },
+}
+impl WireMessage for ServiceHelloReply {
+    const KIND: MessageKind = MessageKind::ServiceHelloReply;
+    const VERSIONED: bool = false;
+}
+
+pub fn negotiate(service: &ServiceHello, client: &ServiceHello) -> ServiceHelloReply {
+    let incompatible = |reason: String| ServiceHelloReply::Incompatible {
+        protocol_version: service.protocol_version,
+        min_protocol_version: service.min_protocol_version,
+        build: service.build.clone(),
+        reason,
+    };
+    if client.protocol_version < service.min_protocol_version {
+        return incompatible(format!("client protocol {} is older than the oldest protocol the service accepts ({})", client.protocol_version, service.min_protocol_version));
+    }
+    if service.protocol_version < client.min_protocol_version {
+        return incompatible(format!("service protocol {} is older than the oldest protocol the client accepts ({})", service.protocol_version, client.min_protocol_version));
+    }
+    if client.protocol_version != service.protocol_version {
+        return incompatible(format!("client speaks protocol {}, service speaks {}", client.protocol_version, service.protocol_version));
+    }
+    let capabilities = ServiceCapabilities::from_bits_truncate(service.capabilities & client.capabilities);
+    let required = ServiceCapabilities::required();
+    if !capabilities.contains(required) {
+        return incompatible(format!("missing required capabilities {:?}", required - capabilities));
+    }
+    ServiceHelloReply::Welcome {
+        protocol_version: service.protocol_version,
+        capabilities: capabilities.bits(),
+        build: service.build.clone(),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DriverState {
+    Enabled,
+    Disabled,
+    NotPresent,
+    Unknown,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientToServiceMsg {
+    Authenticate {
+        mac: AuthMac,
+    },
+    ProcessId {
+        client_id: u32,
+        window_id: u32,
+    },
+    EnableDriver,
+    DisableDriver,
+    QueryDriverStatus,
+    SetResolution {
+        width: u32,
+        height: u32,
+    },
+    Heartbeat,
+    Shutdown,
+}
+impl WireMessage for ClientToServiceMsg {
+    const KIND: MessageKind = MessageKind::ClientToService;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServiceToClientMsg {
+    Challenge {
+        nonce: Nonce,
+    },
+    AuthResult {
+        accepted: bool,
+    },
+    DisplaySetupComplete(isize),
+    DriverStatus {
+        state: DriverState,
+        sessions: u32,
+    },
+    Ack,
+    HeartbeatAck,
+    Failed(String),
+    ShuttingDown,
+}
+impl WireMessage for ServiceToClientMsg {
+    const KIND: MessageKind = MessageKind::ServiceToClient;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{CodecError, decode, encode, FrameHeader};
+    use super::*;
+
+    fn hello(protocol_version: u16, min_protocol_version: u16, capabilities: ServiceCapabilities) -> ServiceHello {
+        ServiceHello { protocol_version, min_protocol_version, capabilities: capabilities.bits(), build: String::from("test"), driver_name: None }
+    }
+    fn is_incompatible(reply: &ServiceHelloReply) -> bool {
+        matches!(reply, ServiceHelloReply::Incompatible { .. })
+    }
+
+    #[test]
+    fn handshake_bypasses_the_version_check() {
+        let other = PROTOCOL_VERSION + 1;
+        let mut bytes = encode(&hello(other, other, ServiceCapabilities::supported())).unwrap();
+        bytes[4..6].copy_from_slice(&other.to_le_bytes());
+        assert_eq!(FrameHeader::from_bytes(&bytes).unwrap().version, other);
+        let decoded = decode::<ServiceHello>(&bytes).unwrap();
+        assert_eq!(decoded.protocol_version, other);
+        assert!(is_incompatible(&negotiate(&ServiceHello::ours(), &decoded)));
+
+        let mut bytes = encode(&ClientToServiceMsg::Heartbeat).unwrap();
+        bytes[4..6].copy_from_slice(&other.to_le_bytes());
+        assert!(matches!(decode::<ClientToServiceMsg>(&bytes), Err(CodecError::VersionMismatch { .. })));
+    }
+
+    #[test]
+    fn negotiate_matching_peers() {
+        let client = hello(PROTOCOL_VERSION, MIN_SERVICE_PROTOCOL_VERSION, ServiceCapabilities::required() | ServiceCapabilities::HEARTBEAT);
+        match negotiate(&ServiceHello::ours(), &client) {
+            ServiceHelloReply::Welcome { protocol_version, capabilities, .. } => {
+                assert_eq!(protocol_version, PROTOCOL_VERSION);
+                assert_eq!(ServiceCapabilities::from_bits_truncate(capabilities), ServiceCapabilities::required() | ServiceCapabilities::HEARTBEAT);
+            }
+            reply => panic!("expected welcome, got {reply:?}"),
+        }
+    }
+
+    #[test]
+    fn negotiate_rejects_incompatible_versions() {
+        let service = ServiceHello::ours();
+        assert!(is_incompatible(&negotiate(&service, &hello(MIN_SERVICE_PROTOCOL_VERSION - 1, 1, ServiceCapabilities::supported()))));
+        assert!(is_incompatible(&negotiate(&service, &hello(PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 1, ServiceCapabilities::supported()))));
+        assert!(is_incompatible(&negotiate(&service, &hello(PROTOCOL_VERSION + 1, 1, ServiceCapabilities::supported()))));
+    }
+
+    #[test]
+    fn negotiate_requires_capabilities() {
+        let client = hello(PROTOCOL_VERSION, MIN_SERVICE_PROTOCOL_VERSION, ServiceCapabilities::DISPLAY_DRIVER);
+        match negotiate(&ServiceHello::ours(), &client) {
+            ServiceHelloReply::Incompatible { reason, .. } => assert!(reason.contains("AUTHENTICATED")),
+            reply => panic!("expected incompatible, got {reply:?}"),
+        }
+    }
+
+    #[test]
+    fn older_peers_read_the_hello_prefix() {
+        #[derive(Serialize, Deserialize)]
+        struct FrozenHello {
+            protocol_version: u16,
+            min_protocol_version: u16,
+            capabilities: u32,
+            build: String,
+        }
+        impl WireMessage for FrozenHello {
+            const KIND: MessageKind = MessageKind::ServiceHello;
+            const VERSIONED: bool = false;
+        }
+        let ours = ServiceHello { driver_name: Some(String::from("Virtual Display")), ..ServiceHello::ours() };
+        let bytes = encode(&ours).unwrap();
+        assert_eq!(decode::<ServiceHello>(&bytes).unwrap(), ours);
+        let frozen = decode::<FrozenHello>(&bytes).unwrap();
+        assert_eq!((frozen.protocol_version, frozen.min_protocol_version, frozen.capabilities), (ours.protocol_version, ours.min_protocol_version, ours.capabilities));
+        assert_eq!(frozen.build, ours.build);
+    }
+}